@@ -62,9 +62,6 @@
                 "emfcamp-schedule-api-0.0.1" = "sha256-okre8nWBh7+/UvdIUus36l5LN1jWQKKia0n2uywAIC8=";
               };
             };
-
-            # Nothing to test
-            doCheck = false;
           };
 
           container-image = pkgs.dockerTools.buildImage {
@@ -0,0 +1,71 @@
+use crate::event_news::RubricNews;
+use serde_json::json;
+use tokio::sync::broadcast;
+
+/// A step in the announcement pipeline.
+#[derive(Debug, Clone)]
+pub(crate) enum Activity {
+    /// The announcer reported an event that is due to be announced
+    Scheduled { event_id: u32, title: String },
+    /// News was built for an event
+    Formatted { event_id: u32, news: RubricNews },
+    /// News was sent to DAPNET
+    Sent { news: RubricNews },
+    /// News could not be sent to DAPNET
+    Failed { news: RubricNews, error: String },
+}
+
+impl Activity {
+    pub(crate) fn to_json(&self) -> serde_json::Value {
+        match self {
+            Self::Scheduled { event_id, title } => json!({
+                "activity": "scheduled",
+                "event_id": event_id,
+                "title": title,
+            }),
+            Self::Formatted { event_id, news } => json!({
+                "activity": "formatted",
+                "event_id": event_id,
+                "rubric": news.rubric,
+                "number": news.number,
+                "text": news.text,
+            }),
+            Self::Sent { news } => json!({
+                "activity": "sent",
+                "rubric": news.rubric,
+                "number": news.number,
+                "text": news.text,
+            }),
+            Self::Failed { news, error } => json!({
+                "activity": "failed",
+                "rubric": news.rubric,
+                "number": news.number,
+                "text": news.text,
+                "error": error,
+            }),
+        }
+    }
+}
+
+/// Distributes pipeline activity to live subscribers (e.g. observability clients).
+#[derive(Clone)]
+pub(crate) struct ActivityFeed {
+    sender: broadcast::Sender<Activity>,
+}
+
+impl ActivityFeed {
+    pub(crate) fn new() -> Self {
+        Self {
+            sender: broadcast::channel(64).0,
+        }
+    }
+
+    pub(crate) fn publish(&self, activity: Activity) {
+        // Having no subscribers is the normal case, not an error
+        let _ = self.sender.send(activity);
+    }
+
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<Activity> {
+        self.sender.subscribe()
+    }
+}
@@ -0,0 +1,152 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// An announcement requested by another system by dropping a JSON file into the ad-hoc
+/// announcement directory.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct AdhocAnnouncement {
+    pub(crate) text: String,
+    pub(crate) target: AdhocTarget,
+
+    /// When to send the announcement, as soon as possible if not set
+    pub(crate) send_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub(crate) enum AdhocTarget {
+    /// A slot in the announcement rubric
+    News { number: i8 },
+    /// A call to individual recipients
+    Call {
+        recipients: Vec<String>,
+        /// The configured transmitter groups are used if not given
+        #[serde(default)]
+        transmitter_groups: Option<Vec<String>>,
+    },
+}
+
+/// Collects the announcements in `path` (a directory of `.json` files or a single file) that are
+/// due to be sent.
+///
+/// Files for announcements that are due are removed, files that cannot be parsed are renamed with
+/// an `.invalid` suffix so they are not read again and files for announcements that are not yet
+/// due are left alone.
+pub(crate) fn take_due(path: &Path, now: DateTime<Utc>) -> Vec<AdhocAnnouncement> {
+    let files: Vec<PathBuf> = if path.is_dir() {
+        match std::fs::read_dir(path) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+                .collect(),
+            Err(e) => {
+                warn!("Failed to read ad-hoc announcement directory: {e}");
+                return Vec::new();
+            }
+        }
+    } else if path.exists() {
+        vec![path.to_path_buf()]
+    } else {
+        Vec::new()
+    };
+
+    let mut due = Vec::new();
+
+    for file in files {
+        let announcement = match std::fs::read_to_string(&file)
+            .map_err(anyhow::Error::from)
+            .and_then(|contents| Ok(serde_json::from_str::<AdhocAnnouncement>(&contents)?))
+        {
+            Ok(announcement) => announcement,
+            Err(e) => {
+                warn!("Invalid ad-hoc announcement {}: {e}", file.display());
+                let mut invalid = file.clone().into_os_string();
+                invalid.push(".invalid");
+                if let Err(e) = std::fs::rename(&file, invalid) {
+                    warn!("Failed to rename invalid ad-hoc announcement: {e}");
+                }
+                continue;
+            }
+        };
+
+        if announcement.send_at.is_some_and(|send_at| send_at > now) {
+            continue;
+        }
+
+        info!("Ad-hoc announcement {} is due", file.display());
+        match std::fs::remove_file(&file) {
+            Ok(()) => due.push(announcement),
+            Err(e) => warn!("Failed to remove ad-hoc announcement, not sending it: {e}"),
+        }
+    }
+
+    due
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates an empty directory for a test's announcement files.
+    fn directory(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("adhoc-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        path
+    }
+
+    #[test]
+    fn due_announcements_are_taken() {
+        let dir = directory("due");
+        let now: DateTime<Utc> = "2024-05-30T14:00:00Z".parse().unwrap();
+
+        std::fs::write(
+            dir.join("now.json"),
+            r#"{"text": "Bar closing", "target": {"type": "news", "number": 3}}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("later.json"),
+            r#"{"text": "Fireworks", "target": {"type": "call", "recipients": ["m0nxn"]}, "send_at": "2024-05-30T21:00:00Z"}"#,
+        )
+        .unwrap();
+
+        let due = take_due(&dir, now);
+
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].text, "Bar closing");
+        assert!(matches!(due[0].target, AdhocTarget::News { number: 3 }));
+        assert!(!dir.join("now.json").exists());
+        assert!(dir.join("later.json").exists());
+
+        let due = take_due(&dir, "2024-05-30T21:00:00Z".parse().unwrap());
+
+        assert_eq!(due.len(), 1);
+        assert!(matches!(
+            &due[0].target,
+            AdhocTarget::Call { recipients, transmitter_groups: None } if recipients == &["m0nxn"]
+        ));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn invalid_announcements_are_set_aside() {
+        let dir = directory("invalid");
+
+        std::fs::write(dir.join("bad.json"), r#"{"text": "No target"}"#).unwrap();
+
+        assert!(take_due(&dir, Utc::now()).is_empty());
+        assert!(!dir.join("bad.json").exists());
+        assert!(dir.join("bad.json.invalid").exists());
+
+        // Set aside files are not read again
+        assert!(take_due(&dir, Utc::now()).is_empty());
+        assert!(dir.join("bad.json.invalid").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,43 @@
+use crate::{
+    dapnet::Dapnet,
+    event_news::{AnnouncementKind, EventExt, NewsSettings},
+    retry::{self, RetryPolicy},
+    schedule_source::ScheduleSource,
+};
+use tracing::info;
+
+/// Looks up a single event in the schedule and prints its announcement, sending it if a DAPNET
+/// client is given.
+pub(crate) async fn run(
+    schedule: &ScheduleSource,
+    news_settings: &NewsSettings,
+    id: u32,
+    kind: AnnouncementKind,
+    dapnet: Option<(&Dapnet, &RetryPolicy)>,
+) -> anyhow::Result<()> {
+    let events = schedule.events().await?;
+
+    let event = events
+        .iter()
+        .find(|event| event.id == id)
+        .ok_or_else(|| anyhow::anyhow!("No event with ID {id} in the schedule"))?;
+
+    let news = event
+        .to_rubric_news(news_settings, kind)
+        .ok_or_else(|| anyhow::anyhow!("Event {id} is not announced (it has no rubric slot)"))?;
+
+    println!(
+        "{}/{}: {}",
+        news.rubric,
+        news.number.unwrap_or(1),
+        news.text
+    );
+
+    if let Some((dapnet, retry_policy)) = dapnet {
+        let outgoing = news.to_outgoing()?;
+        retry::send(retry_policy, "news", || dapnet.new_news(&outgoing)).await?;
+        info!("News sent");
+    }
+
+    Ok(())
+}
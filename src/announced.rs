@@ -0,0 +1,209 @@
+use crate::event_news::AnnouncementKind;
+use chrono::{DateTime, FixedOffset, Utc};
+use emfcamp_schedule_api::schedule::event::Event;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::Path};
+
+/// What is already known about an event that is about to be announced.
+pub(crate) enum AnnouncedState {
+    /// The event has not been announced before
+    New,
+    /// The event has already been announced with the same start time
+    AlreadyAnnounced,
+    /// The event has already been announced with a different start time
+    Moved {
+        previous_start: DateTime<FixedOffset>,
+    },
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct AnnouncedEvent {
+    start: DateTime<FixedOffset>,
+    end: DateTime<FixedOffset>,
+    speaker: String,
+    announced_at: DateTime<Utc>,
+}
+
+/// An announced event as it is stored in the state file.
+#[derive(Serialize, Deserialize)]
+struct StoredEvent {
+    id: u32,
+    starting_now: bool,
+    #[serde(flatten)]
+    event: AnnouncedEvent,
+}
+
+/// Remembers which events have been announced, keyed on the schedule's event ID so that
+/// changes to other details of an event do not make it look like a new event.
+#[derive(Default)]
+pub(crate) struct AnnouncedEvents {
+    events: HashMap<(u32, bool), AnnouncedEvent>,
+}
+
+impl AnnouncedEvents {
+    /// Loads the events announced before a restart from a state file, starting with no events if
+    /// the file does not exist yet.
+    pub(crate) fn load(path: &Path) -> anyhow::Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read state file {}: {e}", path.display()))?;
+        let stored: Vec<StoredEvent> = serde_json::from_str(&contents)
+            .map_err(|e| anyhow::anyhow!("Failed to parse state file {}: {e}", path.display()))?;
+
+        Ok(Self {
+            events: stored
+                .into_iter()
+                .map(|stored| ((stored.id, stored.starting_now), stored.event))
+                .collect(),
+        })
+    }
+
+    /// Writes the announced events to a state file, replacing it in one step so that a crash
+    /// while saving does not leave a partially written file.
+    pub(crate) fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let stored: Vec<StoredEvent> = self
+            .events
+            .iter()
+            .map(|(&(id, starting_now), event)| StoredEvent {
+                id,
+                starting_now,
+                event: event.clone(),
+            })
+            .collect();
+
+        let temp_path = path.with_extension("tmp");
+        std::fs::write(&temp_path, serde_json::to_string_pretty(&stored)?)?;
+        std::fs::rename(&temp_path, path)?;
+
+        Ok(())
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Checks an event against those already announced.
+    pub(crate) fn check(&self, event: &Event, kind: AnnouncementKind) -> AnnouncedState {
+        match self.events.get(&key(event, kind)) {
+            None => AnnouncedState::New,
+            Some(previous) if previous.start == event.start => AnnouncedState::AlreadyAnnounced,
+            Some(previous) => AnnouncedState::Moved {
+                previous_start: previous.start,
+            },
+        }
+    }
+
+    /// Records an event as announced.
+    pub(crate) fn record(&mut self, event: &Event, kind: AnnouncementKind, now: DateTime<Utc>) {
+        self.events.insert(
+            key(event, kind),
+            AnnouncedEvent {
+                start: event.start,
+                end: event.end,
+                speaker: event.speaker.clone(),
+                announced_at: now,
+            },
+        );
+    }
+
+    /// Finds events that have been announced but have not yet ended whose speaker has since
+    /// changed, then records the new speaker.
+    pub(crate) fn speaker_changes<'a>(
+        &mut self,
+        events: &'a [Event],
+        now: DateTime<Utc>,
+    ) -> Vec<&'a Event> {
+        events
+            .iter()
+            .filter(|event| event.end.with_timezone(&Utc) > now)
+            .filter(|event| match self.events.get_mut(&(event.id, false)) {
+                Some(announced) if announced.speaker != event.speaker => {
+                    announced.speaker = event.speaker.clone();
+                    true
+                }
+                _ => false,
+            })
+            .collect()
+    }
+
+    /// Checks if an event has been announced, in any way.
+    pub(crate) fn contains(&self, id: u32) -> bool {
+        self.events.contains_key(&(id, false)) || self.events.contains_key(&(id, true))
+    }
+
+    /// Forgets events that have ended, as they will not be announced again.
+    pub(crate) fn prune(&mut self, now: DateTime<Utc>) {
+        self.events.retain(|_, e| e.end.with_timezone(&Utc) > now);
+    }
+}
+
+fn key(event: &Event, kind: AnnouncementKind) -> (u32, bool) {
+    (event.id, matches!(kind, AnnouncementKind::StartingNow))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(id: u32, start: &str, end: &str) -> Event {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "start_date": start,
+            "end_date": end,
+            "venue": "Stage A",
+            "title": "Opening ceremony",
+            "speaker": "",
+            "description": "",
+            "type": "talk",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn ended_events_are_pruned() {
+        let now: DateTime<Utc> = "2024-05-30T15:00:00Z".parse().unwrap();
+        let ended = event(1, "2024-05-30T14:00:00+01:00", "2024-05-30T15:00:00+01:00");
+        let running = event(2, "2024-05-30T15:00:00+01:00", "2024-05-30T17:00:00+01:00");
+
+        let mut announced = AnnouncedEvents::default();
+        announced.record(&ended, AnnouncementKind::Upcoming, now);
+        announced.record(&running, AnnouncementKind::StartingNow, now);
+
+        announced.prune(now);
+
+        assert_eq!(announced.len(), 1);
+        assert!(!announced.contains(1));
+        assert!(announced.contains(2));
+    }
+
+    #[test]
+    fn moved_events_are_detected() {
+        let now: DateTime<Utc> = "2024-05-30T12:00:00Z".parse().unwrap();
+        let original = event(1, "2024-05-30T14:00:00+01:00", "2024-05-30T15:00:00+01:00");
+        let moved = event(1, "2024-05-30T16:00:00+01:00", "2024-05-30T17:00:00+01:00");
+
+        let mut announced = AnnouncedEvents::default();
+        assert!(matches!(
+            announced.check(&original, AnnouncementKind::Upcoming),
+            AnnouncedState::New
+        ));
+
+        announced.record(&original, AnnouncementKind::Upcoming, now);
+
+        assert!(matches!(
+            announced.check(&original, AnnouncementKind::Upcoming),
+            AnnouncedState::AlreadyAnnounced
+        ));
+        assert!(matches!(
+            announced.check(&moved, AnnouncementKind::Upcoming),
+            AnnouncedState::Moved { previous_start } if previous_start == original.start
+        ));
+        assert!(matches!(
+            announced.check(&original, AnnouncementKind::StartingNow),
+            AnnouncedState::New
+        ));
+    }
+}
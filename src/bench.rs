@@ -0,0 +1,103 @@
+use crate::{
+    clock::MockClock,
+    event_news::AnnouncementKind,
+    notifier::{Announcement, Notifier},
+    tiers::Delivery,
+    AnnouncementState, Context, SendOutcome,
+};
+use chrono::{DateTime, Duration, Utc};
+use emfcamp_schedule_api::{announcer::AnnouncerPollResult, schedule::event::Event};
+use futures_util::future::LocalBoxFuture;
+use std::{path::Path, time::Instant};
+
+/// Accepts every announcement and discards it, so that announcements are handled exactly as they
+/// would be without anything being sent.
+pub(crate) struct NullNotifier;
+
+impl Notifier for NullNotifier {
+    fn name(&self) -> &str {
+        "null"
+    }
+
+    fn accepts(&self, _event: &Event, _delivery: Delivery) -> bool {
+        true
+    }
+
+    fn prepare(
+        &self,
+        _ctx: &Context,
+        _state: &mut AnnouncementState,
+        _kind: AnnouncementKind,
+        _announcement: &Announcement,
+        _event_start: DateTime<Utc>,
+    ) -> bool {
+        true
+    }
+
+    fn notify<'a>(
+        &'a self,
+        _ctx: &'a Context,
+        announcement: &'a Announcement,
+    ) -> LocalBoxFuture<'a, SendOutcome> {
+        Box::pin(async move {
+            std::hint::black_box(announcement);
+            SendOutcome::Sent
+        })
+    }
+}
+
+/// Handles every event in a schedule file as the announcer would, as fast as possible, reporting
+/// throughput and how many events were and were not announced.
+///
+/// Each event is handled as if at the time it would be announced, using `clock` (which must be
+/// the clock `ctx` uses), and each iteration starts with nothing announced.
+pub(crate) async fn run(
+    ctx: &Context,
+    schedule: &Path,
+    iterations: usize,
+    event_start_offset: Duration,
+    clock: &MockClock,
+) -> anyhow::Result<()> {
+    let mut events: Vec<Event> = serde_json::from_reader(std::fs::File::open(schedule)?)?;
+    events.sort_by_key(|event| event.start);
+    println!("Loaded {} events from {}", events.len(), schedule.display());
+
+    let mut announced = 0usize;
+    let mut not_announced = 0usize;
+
+    let start = Instant::now();
+
+    for _ in 0..iterations {
+        let mut state = AnnouncementState::new(ctx)?;
+
+        for event in &events {
+            clock.set(event.start.with_timezone(&Utc) + event_start_offset - ctx.cli.time_offset);
+
+            let msg = Ok(AnnouncerPollResult::Event(event.clone()));
+            if crate::handle_announcer_event(ctx, &mut state, AnnouncementKind::Upcoming, msg).await
+            {
+                announced += 1;
+            } else {
+                not_announced += 1;
+            }
+        }
+    }
+
+    let elapsed = start.elapsed();
+
+    println!(
+        "Handled {} events in {elapsed:?} ({iterations} iterations)",
+        announced + not_announced
+    );
+    println!(
+        "Throughput: {:.0} events/s",
+        (announced + not_announced) as f64 / elapsed.as_secs_f64()
+    );
+    println!(
+        "Announced: {}, not announced: {}",
+        announced / iterations.max(1),
+        not_announced / iterations.max(1)
+    );
+
+    Ok(())
+}
@@ -0,0 +1,29 @@
+use metrics::{describe_gauge, gauge};
+
+pub(crate) const GIT_HASH: &str = env!("GIT_HASH");
+pub(crate) const BUILD_DATE: &str = env!("BUILD_DATE");
+pub(crate) const ENABLED_FEATURES: &str = env!("ENABLED_FEATURES");
+
+pub(crate) const LONG_VERSION: &str = concat!(
+    env!("CARGO_PKG_VERSION"),
+    " (git ",
+    env!("GIT_HASH"),
+    ", built ",
+    env!("BUILD_DATE"),
+    ", features: ",
+    env!("ENABLED_FEATURES"),
+    ")"
+);
+
+/// Exposes the build information as a metric.
+pub(crate) fn register_metric() {
+    describe_gauge!("build_info", "Information about the running build");
+    gauge!(
+        "build_info",
+        "version" => env!("CARGO_PKG_VERSION"),
+        "git_hash" => GIT_HASH,
+        "build_date" => BUILD_DATE,
+        "features" => ENABLED_FEATURES
+    )
+    .set(1.0);
+}
@@ -0,0 +1,391 @@
+use crate::{
+    dapnet::Dapnet,
+    message_length::{self, OverlongPolicy},
+    pocsag::CharsetSettings,
+    retry::{self, RetryPolicy},
+};
+use dapnet_api::{OutgoingCall, OutgoingCallBuilder};
+use metrics::counter;
+use std::collections::HashMap;
+use tokio::time::Instant;
+use tracing::{info, warn};
+
+/// Where calls are sent, DAPNET other than in tests.
+pub(crate) trait CallSender {
+    async fn new_call(&self, call: &OutgoingCall) -> anyhow::Result<()>;
+}
+
+impl CallSender for Dapnet {
+    async fn new_call(&self, call: &OutgoingCall) -> anyhow::Result<()> {
+        Dapnet::new_call(self, call).await
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct CallSettings {
+    pub(crate) max_recipients_per_call: usize,
+
+    /// If not empty, only these callsigns may be sent calls
+    pub(crate) recipient_allowlist: Vec<String>,
+
+    /// Callsigns that must never be sent calls
+    pub(crate) recipient_blocklist: Vec<String>,
+
+    /// Transmitter groups calls are sent via unless more specific ones are given
+    pub(crate) transmitter_groups: Vec<String>,
+
+    /// How characters that cannot be sent via POCSAG are handled
+    pub(crate) charset: CharsetSettings,
+
+    /// Maximum length of the text of a call
+    pub(crate) max_length: usize,
+
+    /// What to do with calls longer than the maximum length
+    pub(crate) overlong_policy: OverlongPolicy,
+
+    /// How calls are timed out and retried
+    pub(crate) retry: RetryPolicy,
+}
+
+impl CallSettings {
+    fn is_permitted(&self, recipient: &str) -> bool {
+        let allowed = self.recipient_allowlist.is_empty()
+            || self
+                .recipient_allowlist
+                .iter()
+                .any(|r| r.eq_ignore_ascii_case(recipient));
+
+        let blocked = self
+            .recipient_blocklist
+            .iter()
+            .any(|r| r.eq_ignore_ascii_case(recipient));
+
+        allowed && !blocked
+    }
+}
+
+/// A call as it is sent, once recipients that are not permitted have been removed and the text
+/// has been made POCSAG safe and fitted to the maximum length.
+pub(crate) struct PreparedCall {
+    /// Text of each call to send, more than one if the text was split to fit
+    pub(crate) parts: Vec<String>,
+
+    pub(crate) recipients: Vec<String>,
+}
+
+/// Prepares a call for sending.
+///
+/// Text is made POCSAG safe, then text longer than the maximum length is truncated or split into
+/// several numbered calls, depending on the overlong policy.
+///
+/// Recipients not permitted by the allowlist/blocklist are removed, it is an error if there are
+/// none left.
+pub(crate) fn prepare_call(
+    settings: &CallSettings,
+    text: &str,
+    recipients: &[String],
+) -> anyhow::Result<PreparedCall> {
+    let recipients: Vec<String> = recipients
+        .iter()
+        .filter(|r| {
+            let permitted = settings.is_permitted(r);
+            if !permitted {
+                warn!("Removing recipient {r} from call as it is not permitted");
+                counter!("dapnet_blocked_recipients").increment(1);
+            }
+            permitted
+        })
+        .cloned()
+        .collect();
+
+    if recipients.is_empty() {
+        return Err(anyhow::anyhow!("Call has no permitted recipients"));
+    }
+
+    let text = settings.charset.sanitise(text.to_string());
+    let parts = message_length::fit(&text, settings.max_length, settings.overlong_policy);
+
+    Ok(PreparedCall { parts, recipients })
+}
+
+/// Sends a call to a list of recipients, splitting it into several calls when there are more
+/// recipients than DAPNET accepts in one.
+///
+/// The call is prepared first (see [`prepare_call`]).
+///
+/// Each chunk is retried according to the retry policy. Chunks that still fail are then retried
+/// once more, calling each recipient in the chunk separately so that one bad recipient does not
+/// stop the rest from being paged. Chunks that were sent successfully are not resent.
+///
+/// Every part of a call that was split is sent to every recipient it can be, even if an earlier
+/// part could not be, and the error lists which parts did not reach which recipients.
+pub(crate) async fn send_call(
+    dapnet: &impl CallSender,
+    settings: &CallSettings,
+    text: &str,
+    recipients: &[String],
+    transmitter_groups: &[String],
+) -> anyhow::Result<()> {
+    let call = prepare_call(settings, text, recipients)?;
+
+    // Every part and recipient shares the retry time limit, so that a call to many recipients
+    // cannot hold up sending for many times the limit
+    let deadline = Instant::now() + settings.retry.max_duration;
+
+    if call.parts.len() > 1 {
+        info!("Sending call as {} parts", call.parts.len());
+    }
+
+    let mut failed_parts = Vec::new();
+
+    for (i, part) in call.parts.iter().enumerate() {
+        let failed_recipients = send_call_part(
+            dapnet,
+            settings,
+            part,
+            &call.recipients,
+            transmitter_groups,
+            deadline,
+        )
+        .await;
+
+        if !failed_recipients.is_empty() {
+            failed_parts.push((i + 1, failed_recipients));
+        }
+    }
+
+    // A recipient is only counted as sent the call if they were sent every part of it
+    let mut missed_parts = HashMap::<&str, usize>::new();
+    for recipient in failed_parts.iter().flat_map(|(_, recipients)| recipients) {
+        *missed_parts.entry(recipient).or_default() += 1;
+    }
+
+    let failed = missed_parts
+        .values()
+        .filter(|&&missed| missed == call.parts.len())
+        .count();
+    let partial = missed_parts.len() - failed;
+    let sent = call.recipients.len() - missed_parts.len();
+
+    counter!("dapnet_call_recipients", "result" => "sent").increment(sent as u64);
+    counter!("dapnet_call_recipients", "result" => "partial").increment(partial as u64);
+    counter!("dapnet_call_recipients", "result" => "failed").increment(failed as u64);
+
+    if failed_parts.is_empty() {
+        counter!("dapnet_calls", "result" => "ok").increment(1);
+        return Ok(());
+    }
+
+    let result = if sent + partial > 0 {
+        "partial"
+    } else {
+        "failed"
+    };
+    counter!("dapnet_calls", "result" => result).increment(1);
+
+    let failures: Vec<String> = failed_parts
+        .iter()
+        .map(|(part, recipients)| {
+            if call.parts.len() > 1 {
+                format!(
+                    "part {part}/{} not sent to {}",
+                    call.parts.len(),
+                    recipients.join(", ")
+                )
+            } else {
+                format!("not sent to {}", recipients.join(", "))
+            }
+        })
+        .collect();
+
+    Err(anyhow::anyhow!(
+        "Call sent in full to {sent} of {} recipients, {}",
+        call.recipients.len(),
+        failures.join("; ")
+    ))
+}
+
+/// Sends one part of a call to every recipient, returning those it could not be sent to.
+async fn send_call_part(
+    dapnet: &impl CallSender,
+    settings: &CallSettings,
+    text: &str,
+    recipients: &[String],
+    transmitter_groups: &[String],
+    deadline: Instant,
+) -> Vec<String> {
+    let chunks: Vec<&[String]> = recipients
+        .chunks(settings.max_recipients_per_call.max(1))
+        .collect();
+
+    let mut failed = Vec::new();
+
+    for (i, chunk) in chunks.iter().enumerate() {
+        match send_chunk(dapnet, settings, text, chunk, transmitter_groups, deadline).await {
+            Ok(()) => {
+                counter!("dapnet_call_chunks", "result" => "ok").increment(1);
+            }
+            Err(e) => {
+                warn!("Failed to send call chunk {}/{}: {e}", i + 1, chunks.len());
+                counter!("dapnet_call_chunks", "result" => "failed").increment(1);
+                failed.push(*chunk);
+            }
+        }
+    }
+
+    if !failed.is_empty() {
+        info!(
+            "Retrying {} of {} failed call chunks",
+            failed.len(),
+            chunks.len()
+        );
+    }
+
+    let mut failed_recipients = Vec::new();
+
+    for recipient in failed.into_iter().flatten() {
+        let chunk = std::slice::from_ref(recipient);
+        if let Err(e) =
+            send_chunk(dapnet, settings, text, chunk, transmitter_groups, deadline).await
+        {
+            warn!("Failed to send call to {recipient} on retry: {e}");
+            failed_recipients.push(recipient.clone());
+        }
+    }
+
+    failed_recipients
+}
+
+async fn send_chunk(
+    dapnet: &impl CallSender,
+    settings: &CallSettings,
+    text: &str,
+    recipients: &[String],
+    transmitter_groups: &[String],
+    deadline: Instant,
+) -> anyhow::Result<()> {
+    let call = OutgoingCallBuilder::default()
+        .text(text.to_string())
+        .recipients(recipients.to_vec())
+        .transmitter_groups(transmitter_groups.to_vec())
+        .build()?;
+
+    retry::send_until(&settings.retry, "call", deadline, || dapnet.new_call(&call)).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pocsag::CharsetPolicy;
+    use std::{sync::Mutex, time::Duration};
+
+    /// Accepts calls unless they are for a part and recipient it was told to fail, keeping the
+    /// text and recipients of each call it accepts.
+    #[derive(Default)]
+    struct FakeDapnet {
+        fail: Vec<(&'static str, &'static str)>,
+        sent: Mutex<Vec<(String, Vec<String>)>>,
+    }
+
+    impl CallSender for FakeDapnet {
+        async fn new_call(&self, call: &OutgoingCall) -> anyhow::Result<()> {
+            let call = serde_json::to_value(call)?;
+            let text = call["text"].as_str().unwrap().to_string();
+            let recipients: Vec<String> = serde_json::from_value(call["callSignNames"].clone())?;
+
+            if self.fail.iter().any(|(part, recipient)| {
+                text.starts_with(part) && recipients.iter().any(|r| r == recipient)
+            }) {
+                return Err(anyhow::anyhow!("Failed"));
+            }
+
+            self.sent.lock().unwrap().push((text, recipients));
+            Ok(())
+        }
+    }
+
+    fn settings() -> CallSettings {
+        CallSettings {
+            max_recipients_per_call: 2,
+            recipient_allowlist: Vec::new(),
+            recipient_blocklist: Vec::new(),
+            transmitter_groups: Vec::new(),
+            charset: CharsetSettings {
+                policy: CharsetPolicy::Replace,
+                transliterate: false,
+                replacements: Vec::new(),
+            },
+            max_length: 24,
+            overlong_policy: OverlongPolicy::Split,
+            retry: RetryPolicy {
+                request_timeout: Duration::from_secs(1),
+                max_attempts: 1,
+                initial_delay: Duration::ZERO,
+                max_duration: Duration::from_secs(10),
+            },
+        }
+    }
+
+    fn recipients() -> Vec<String> {
+        ["M0AAA", "M0BBB", "M0CCC"].map(String::from).to_vec()
+    }
+
+    /// Parts of a call each recipient was sent, in the order they were sent.
+    fn parts_sent_to(dapnet: &FakeDapnet, recipient: &str) -> Vec<String> {
+        dapnet
+            .sent
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, recipients)| recipients.iter().any(|r| r == recipient))
+            .map(|(text, _)| text[..3].to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn every_part_is_sent_to_every_recipient() {
+        let dapnet = FakeDapnet::default();
+
+        send_call(
+            &dapnet,
+            &settings(),
+            "The bar is now open until midnight",
+            &recipients(),
+            &[],
+        )
+        .await
+        .unwrap();
+
+        for recipient in recipients() {
+            assert_eq!(parts_sent_to(&dapnet, &recipient), ["1/2", "2/2"]);
+        }
+    }
+
+    #[tokio::test]
+    async fn later_parts_are_sent_when_an_earlier_part_fails() {
+        let dapnet = FakeDapnet {
+            fail: vec![("1/2", "M0BBB")],
+            ..Default::default()
+        };
+
+        let e = send_call(
+            &dapnet,
+            &settings(),
+            "The bar is now open until midnight",
+            &recipients(),
+            &[],
+        )
+        .await
+        .unwrap_err();
+
+        assert_eq!(
+            e.to_string(),
+            "Call sent in full to 2 of 3 recipients, part 1/2 not sent to M0BBB"
+        );
+        assert_eq!(parts_sent_to(&dapnet, "M0AAA"), ["1/2", "2/2"]);
+        assert_eq!(parts_sent_to(&dapnet, "M0BBB"), ["2/2"]);
+        assert_eq!(parts_sent_to(&dapnet, "M0CCC"), ["1/2", "2/2"]);
+    }
+}
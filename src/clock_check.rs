@@ -0,0 +1,43 @@
+use chrono::{DateTime, Duration, Utc};
+use metrics::gauge;
+use tracing::{info, warn};
+use url::Url;
+
+/// Estimates the difference between the local clock and the clock of the server at `url`, using
+/// the Date header of the response (positive if the local clock is ahead).
+async fn measure_clock_skew(http: &reqwest::Client, url: &Url) -> anyhow::Result<Duration> {
+    let request_time = Utc::now();
+    let response = http.head(url.clone()).send().await?;
+    let response_time = Utc::now();
+
+    let server_time = response
+        .headers()
+        .get(reqwest::header::DATE)
+        .ok_or_else(|| anyhow::anyhow!("Response has no Date header"))?
+        .to_str()?;
+    let server_time = DateTime::parse_from_rfc2822(server_time)?.with_timezone(&Utc);
+
+    // Assume the server generated the header half way through the request
+    let local_time = request_time + (response_time - request_time) / 2;
+
+    Ok(local_time - server_time)
+}
+
+/// Checks the local clock against the schedule API, warning if the difference exceeds the
+/// threshold.
+pub(crate) async fn check_clock(http: &reqwest::Client, url: &Url, threshold: Duration) {
+    match measure_clock_skew(http, url).await {
+        Ok(skew) => {
+            gauge!("clock_skew_seconds").set(skew.num_milliseconds() as f64 / 1000.0);
+
+            if skew.abs() > threshold {
+                warn!("Local clock differs from schedule API by {skew}, announcements will be sent at the wrong time");
+            } else {
+                info!("Local clock differs from schedule API by {skew}");
+            }
+        }
+        Err(e) => {
+            warn!("Failed to check clock against schedule API: {e}");
+        }
+    }
+}
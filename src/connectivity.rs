@@ -0,0 +1,19 @@
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tracing::warn;
+
+/// Checks that a TCP connection can be opened to `address` (a "host:port" pair) within the
+/// timeout, as a cheap way to tell if the network is down before making a request.
+pub(crate) async fn is_reachable(address: &str, timeout: Duration) -> bool {
+    match tokio::time::timeout(timeout, TcpStream::connect(address)).await {
+        Ok(Ok(_)) => true,
+        Ok(Err(e)) => {
+            warn!("Cannot connect to {address}: {e}");
+            false
+        }
+        Err(_) => {
+            warn!("Timed out connecting to {address}");
+            false
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use std::{
+    path::Path,
+    process::{Command, Output},
+    time::Duration,
+};
+
+/// Where a credential can be read from, the first one that is set is used.
+pub(crate) struct CredentialSource<'a> {
+    pub(crate) value: Option<&'a str>,
+    pub(crate) file: Option<&'a Path>,
+    pub(crate) command: Option<&'a str>,
+}
+
+/// Gets a credential from its value, a file containing it or the output of a command.
+pub(crate) fn resolve(name: &str, source: CredentialSource) -> anyhow::Result<String> {
+    if let Some(value) = source.value {
+        Ok(value.to_string())
+    } else if let Some(file) = source.file {
+        Ok(std::fs::read_to_string(file)
+            .map_err(|e| anyhow::anyhow!("Failed to read {name} from {}: {e}", file.display()))?
+            .trim_end_matches(['\r', '\n'])
+            .to_string())
+    } else if let Some(command) = source.command {
+        let output = Command::new("sh").arg("-c").arg(command).output()?;
+        command_output(name, output)
+    } else {
+        Err(anyhow::anyhow!("{name} is required"))
+    }
+}
+
+/// Gets a credential from the output of a command without blocking, killing the command if it
+/// has not finished within the timeout.
+pub(crate) async fn resolve_command(
+    name: &str,
+    command: &str,
+    timeout: Duration,
+) -> anyhow::Result<String> {
+    let output = tokio::time::timeout(
+        timeout,
+        tokio::process::Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await
+    .map_err(|_| anyhow::anyhow!("Command to get {name} did not finish within {timeout:?}"))??;
+
+    command_output(name, output)
+}
+
+/// Gets a credential from the first line a command printed.
+fn command_output(name: &str, output: Output) -> anyhow::Result<String> {
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "Command to get {name} failed ({})",
+            output.status
+        ));
+    }
+
+    Ok(String::from_utf8(output.stdout)?
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn command_output_is_used() {
+        let password = resolve_command(
+            "password",
+            "printf 'hunter2\\nextra'",
+            Duration::from_secs(5),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(password, "hunter2");
+    }
+
+    #[tokio::test]
+    async fn slow_commands_time_out() {
+        let result = resolve_command("password", "sleep 5", Duration::from_millis(100)).await;
+
+        assert!(result.is_err());
+    }
+}
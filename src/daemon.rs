@@ -0,0 +1,28 @@
+use daemonize::Daemonize;
+use std::{fs::OpenOptions, path::Path};
+
+/// Detaches from the terminal and continues running in the background, for hosts without a
+/// service manager.
+///
+/// Must be called before the async runtime is started, as only the calling thread survives the
+/// fork.
+pub(crate) fn daemonise(pid_file: Option<&Path>, log_file: Option<&Path>) -> anyhow::Result<()> {
+    // Stay in the current directory so that relative paths given as arguments still work
+    let mut daemon = Daemonize::new().working_directory(std::env::current_dir()?);
+
+    if let Some(pid_file) = pid_file {
+        daemon = daemon.pid_file(pid_file);
+    }
+
+    if let Some(log_file) = log_file {
+        let log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(log_file)?;
+        daemon = daemon.stdout(log.try_clone()?).stderr(log);
+    }
+
+    daemon.start()?;
+
+    Ok(())
+}
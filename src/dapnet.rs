@@ -0,0 +1,387 @@
+use crate::credentials;
+use chrono::DateTime;
+use dapnet_api::{News, OutgoingCall, OutgoingNews, Rubric, TransmitterGroup};
+use metrics::counter;
+use reqwest::{
+    header::{DATE, RETRY_AFTER},
+    Response, StatusCode,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{fmt, sync::Mutex, time::Duration};
+use tracing::{info, warn};
+use url::Url;
+
+pub(crate) const API_URL: &str = "https://hampager.de/api/";
+
+/// How long the password command may take to give a new password
+const PASSWORD_COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// DAPNET asked for requests to be slowed down (HTTP 429).
+#[derive(Debug)]
+pub(crate) struct RateLimited {
+    /// How long DAPNET asked to be left alone for, if it said
+    pub(crate) retry_after: Option<Duration>,
+}
+
+impl fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.retry_after {
+            Some(retry_after) => write!(f, "Rate limited by DAPNET, retry after {retry_after:?}"),
+            None => write!(f, "Rate limited by DAPNET"),
+        }
+    }
+}
+
+impl std::error::Error for RateLimited {}
+
+/// Connection to DAPNET.
+///
+/// Requests are made directly rather than via the DAPNET API client, so that the response headers
+/// are available and the HTTP client (e.g. its timeouts) can be configured, but the API client's
+/// types are used for what is sent and received.
+pub(crate) struct Dapnet {
+    http: reqwest::Client,
+
+    /// DAPNET cores to use, news and calls are sent to every one of them (pagers ignore the
+    /// duplicates) and everything else is read from the first that responds
+    api_urls: Vec<Url>,
+    username: String,
+    password: Mutex<String>,
+
+    /// Command the password came from, which is run again for a new password if DAPNET rejects
+    /// the current one
+    password_command: Option<String>,
+}
+
+impl Dapnet {
+    pub(crate) fn new(username: &str, password: &str) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            api_urls: vec![Url::parse(API_URL).unwrap()],
+            username: username.to_string(),
+            password: Mutex::new(password.to_string()),
+            password_command: None,
+        }
+    }
+
+    /// Makes requests with this HTTP client, e.g. one with timeouts set.
+    pub(crate) fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Uses these DAPNET cores (e.g. the public core and one on site) rather than the public core.
+    pub(crate) fn with_api_urls(mut self, urls: &[Url]) -> Self {
+        if !urls.is_empty() {
+            self.api_urls = urls.iter().map(api_base_url).collect();
+        }
+        self
+    }
+
+    /// Gets the password again from this command if DAPNET rejects it, so that a password that is
+    /// rotated while running is picked up.
+    pub(crate) fn with_password_command(mut self, command: Option<String>) -> Self {
+        self.password_command = command;
+        self
+    }
+
+    pub(crate) fn username(&self) -> &str {
+        &self.username
+    }
+
+    pub(crate) async fn new_news(&self, news: &OutgoingNews) -> anyhow::Result<()> {
+        self.post("news", news).await
+    }
+
+    pub(crate) async fn new_call(&self, call: &OutgoingCall) -> anyhow::Result<()> {
+        self.post("calls", call).await
+    }
+
+    pub(crate) async fn get_rubric(&self, name: &str) -> anyhow::Result<Option<Rubric>> {
+        self.get(&format!("rubrics/{name}")).await
+    }
+
+    pub(crate) async fn get_transmitter_group(
+        &self,
+        name: &str,
+    ) -> anyhow::Result<Option<TransmitterGroup>> {
+        self.get(&format!("transmitterGroups/{name}")).await
+    }
+
+    /// Gets the news in a rubric, which has an entry for each slot (empty slots being null).
+    pub(crate) async fn get_news(&self, rubric: &str) -> anyhow::Result<Option<Vec<News>>> {
+        let news: Option<Vec<Option<News>>> =
+            self.get(&format!("news?rubricName={rubric}")).await?;
+        Ok(news.map(|news| news.into_iter().flatten().collect()))
+    }
+
+    /// Checks if a user is a DAPNET admin, who may post news to any rubric.
+    pub(crate) async fn is_admin(&self, username: &str) -> anyhow::Result<bool> {
+        let user: User = self
+            .get(&format!("users/{username}"))
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("User {username} does not exist"))?;
+
+        Ok(user.admin)
+    }
+
+    /// Gets an item from the API, returning `None` if it does not exist.
+    ///
+    /// Each core is tried in turn until one responds, the error from the last is returned if none
+    /// do.
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> anyhow::Result<Option<T>> {
+        let mut error = None;
+
+        for api_url in &self.api_urls {
+            match self.get_from(api_url, path).await {
+                Ok(item) => return Ok(item),
+                Err(e) => {
+                    if self.api_urls.len() > 1 {
+                        warn!("Failed to get {path} from DAPNET core {api_url}: {e}");
+                    }
+                    error = Some(e);
+                }
+            }
+        }
+
+        Err(error.unwrap_or_else(|| anyhow::anyhow!("No DAPNET cores")))
+    }
+
+    async fn get_from<T: DeserializeOwned>(
+        &self,
+        api_url: &Url,
+        path: &str,
+    ) -> anyhow::Result<Option<T>> {
+        let mut response = self.send_get(api_url, path).await?;
+
+        if response.status() == StatusCode::UNAUTHORIZED && self.refresh_password().await {
+            response = self.send_get(api_url, path).await?;
+        }
+
+        let status = response.status();
+
+        if status.is_success() {
+            Ok(Some(response.json().await?))
+        } else if status == StatusCode::NOT_FOUND {
+            Ok(None)
+        } else {
+            Err(anyhow::anyhow!("DAPNET API error: {status}"))
+        }
+    }
+
+    async fn send_get(&self, api_url: &Url, path: &str) -> anyhow::Result<Response> {
+        let password = self.password.lock().unwrap().clone();
+
+        Ok(self
+            .http
+            .get(api_url.join(path)?)
+            .basic_auth(&self.username, Some(password))
+            .send()
+            .await?)
+    }
+
+    /// Sends an item to every core at once, succeeding if any of them accepted it.
+    ///
+    /// If none did, the error from the first core is returned (so that it is retried as if there
+    /// were only that core).
+    async fn post<T: Serialize + ?Sized>(&self, path: &str, item: &T) -> anyhow::Result<()> {
+        let results = futures_util::future::join_all(
+            self.api_urls
+                .iter()
+                .map(|api_url| self.post_to(api_url, path, item)),
+        )
+        .await;
+
+        let mut sent = false;
+        let mut error = None;
+
+        for (api_url, result) in self.api_urls.iter().zip(results) {
+            let label = if result.is_ok() { "ok" } else { "failed" };
+            counter!("dapnet_core_requests", "core" => api_url.to_string(), "result" => label)
+                .increment(1);
+
+            match result {
+                Ok(()) => sent = true,
+                Err(e) => {
+                    if self.api_urls.len() > 1 {
+                        warn!("Failed to send {path} to DAPNET core {api_url}: {e}");
+                    }
+                    error.get_or_insert(e);
+                }
+            }
+        }
+
+        match error {
+            Some(e) if !sent => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    async fn post_to<T: Serialize + ?Sized>(
+        &self,
+        api_url: &Url,
+        path: &str,
+        item: &T,
+    ) -> anyhow::Result<()> {
+        let mut response = self.send_post(api_url, path, item).await?;
+
+        if response.status() == StatusCode::UNAUTHORIZED && self.refresh_password().await {
+            response = self.send_post(api_url, path, item).await?;
+        }
+
+        let status = response.status();
+
+        if status.is_success() {
+            Ok(())
+        } else if status == StatusCode::TOO_MANY_REQUESTS {
+            Err(RateLimited {
+                retry_after: retry_after(&response),
+            }
+            .into())
+        } else {
+            Err(anyhow::anyhow!("DAPNET API error: {status}"))
+        }
+    }
+
+    async fn send_post<T: Serialize + ?Sized>(
+        &self,
+        api_url: &Url,
+        path: &str,
+        item: &T,
+    ) -> anyhow::Result<Response> {
+        let password = self.password.lock().unwrap().clone();
+
+        Ok(self
+            .http
+            .post(api_url.join(path)?)
+            .basic_auth(&self.username, Some(password))
+            .json(item)
+            .send()
+            .await?)
+    }
+
+    /// Runs the password command again, returning true if it gave a different password.
+    async fn refresh_password(&self) -> bool {
+        let Some(command) = &self.password_command else {
+            return false;
+        };
+
+        info!("DAPNET rejected the password, running the password command again");
+
+        let password = match credentials::resolve_command(
+            "DAPNET password",
+            command,
+            PASSWORD_COMMAND_TIMEOUT,
+        )
+        .await
+        {
+            Ok(password) => password,
+            Err(e) => {
+                warn!("Failed to get a new DAPNET password: {e}");
+                return false;
+            }
+        };
+
+        let mut current = self.password.lock().unwrap();
+        if password == *current {
+            warn!("The password command gave the same password");
+            return false;
+        }
+
+        *current = password;
+
+        true
+    }
+}
+
+/// Makes sure a core's API URL ends with a slash, so that paths are joined on to the end of it
+/// rather than replacing its last part.
+fn api_base_url(url: &Url) -> Url {
+    let mut url = url.clone();
+    if !url.path().ends_with('/') {
+        url.set_path(&format!("{}/", url.path()));
+    }
+    url
+}
+
+/// The parts of a DAPNET user that are used.
+#[derive(Deserialize)]
+struct User {
+    #[serde(default)]
+    admin: bool,
+}
+
+/// Reads the Retry-After header of a response, which is either a number of seconds or a date.
+///
+/// A date is measured from the Date header of the response rather than the local clock, which may
+/// not agree with DAPNET's.
+fn retry_after(response: &Response) -> Option<Duration> {
+    let header = |name| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+    };
+
+    parse_retry_after(header(RETRY_AFTER)?, header(DATE))
+}
+
+fn parse_retry_after(retry_after: &str, date: Option<&str>) -> Option<Duration> {
+    if let Ok(seconds) = retry_after.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let retry_at = DateTime::parse_from_rfc2822(retry_after).ok()?;
+    let date = DateTime::parse_from_rfc2822(date?).ok()?;
+
+    Some((retry_at - date).to_std().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn api_urls_are_joined_on_to() {
+        for url in ["https://dapnet.example/api", "https://dapnet.example/api/"] {
+            assert_eq!(
+                api_base_url(&url.parse().unwrap())
+                    .join("news")
+                    .unwrap()
+                    .as_str(),
+                "https://dapnet.example/api/news"
+            );
+        }
+    }
+
+    #[test]
+    fn retry_after_seconds() {
+        assert_eq!(
+            parse_retry_after("120", None),
+            Some(Duration::from_secs(120))
+        );
+    }
+
+    #[test]
+    fn retry_after_date() {
+        assert_eq!(
+            parse_retry_after(
+                "Wed, 21 Oct 2015 07:30:00 GMT",
+                Some("Wed, 21 Oct 2015 07:28:00 GMT")
+            ),
+            Some(Duration::from_secs(120))
+        );
+    }
+
+    #[test]
+    fn retry_after_date_in_the_past() {
+        assert_eq!(
+            parse_retry_after(
+                "Wed, 21 Oct 2015 07:28:00 GMT",
+                Some("Wed, 21 Oct 2015 07:30:00 GMT")
+            ),
+            Some(Duration::ZERO)
+        );
+    }
+}
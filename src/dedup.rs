@@ -0,0 +1,86 @@
+use chrono::{DateTime, Duration, Utc};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    hash::{DefaultHasher, Hash, Hasher},
+};
+
+/// Remembers recently sent messages so that identical messages to the same target can be
+/// suppressed.
+pub(crate) struct DuplicateSuppressor {
+    window: Duration,
+    sent: HashMap<u64, DateTime<Utc>>,
+}
+
+impl DuplicateSuppressor {
+    pub(crate) fn new(window: Duration) -> Self {
+        Self {
+            window,
+            sent: HashMap::new(),
+        }
+    }
+
+    /// Returns true if the same text was sent to the same target within the window, otherwise
+    /// records it as being sent now.
+    pub(crate) fn check_and_record(
+        &mut self,
+        target: &str,
+        text: &str,
+        now: DateTime<Utc>,
+    ) -> bool {
+        self.sent.retain(|_, sent| now - *sent < self.window);
+
+        let mut hasher = DefaultHasher::new();
+        target.hash(&mut hasher);
+        text.hash(&mut hasher);
+        let key = hasher.finish();
+
+        match self.sent.entry(key) {
+            Entry::Occupied(_) => true,
+            Entry::Vacant(entry) => {
+                entry.insert(now);
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duplicates_within_the_window_are_suppressed() {
+        let mut suppressor = DuplicateSuppressor::new(Duration::minutes(10));
+        let now: DateTime<Utc> = "2024-05-30T14:00:00Z".parse().unwrap();
+
+        assert!(!suppressor.check_and_record("emfcamp/1", "<Stg A> Opening", now));
+        assert!(suppressor.check_and_record(
+            "emfcamp/1",
+            "<Stg A> Opening",
+            now + Duration::minutes(5)
+        ));
+    }
+
+    #[test]
+    fn different_targets_and_texts_are_not_duplicates() {
+        let mut suppressor = DuplicateSuppressor::new(Duration::minutes(10));
+        let now: DateTime<Utc> = "2024-05-30T14:00:00Z".parse().unwrap();
+
+        assert!(!suppressor.check_and_record("emfcamp/1", "<Stg A> Opening", now));
+        assert!(!suppressor.check_and_record("emfcamp/2", "<Stg A> Opening", now));
+        assert!(!suppressor.check_and_record("emfcamp/1", "<Stg A> Closing", now));
+    }
+
+    #[test]
+    fn duplicates_are_sent_again_after_the_window() {
+        let mut suppressor = DuplicateSuppressor::new(Duration::minutes(10));
+        let now: DateTime<Utc> = "2024-05-30T14:00:00Z".parse().unwrap();
+
+        assert!(!suppressor.check_and_record("emfcamp/1", "<Stg A> Opening", now));
+        assert!(!suppressor.check_and_record(
+            "emfcamp/1",
+            "<Stg A> Opening",
+            now + Duration::minutes(10)
+        ));
+    }
+}
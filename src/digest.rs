@@ -0,0 +1,260 @@
+use crate::{
+    event_news::NewsSettings,
+    message_length,
+    tiers::{Tier, TierSettings},
+};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveTime, Utc};
+use emfcamp_schedule_api::schedule::event::Event;
+use std::collections::BTreeMap;
+
+/// Decides when the daily digest is sent, which is once a day at or after the digest time.
+///
+/// The digest time and days are in the schedule's time zone (as given by the offset of its
+/// events) rather than that of the machine the announcer runs on.
+pub(crate) struct DigestScheduler {
+    time: NaiveTime,
+    offset: Option<FixedOffset>,
+    last_sent: Option<NaiveDate>,
+}
+
+impl DigestScheduler {
+    pub(crate) fn new(time: NaiveTime) -> Self {
+        Self {
+            time,
+            offset: None,
+            last_sent: None,
+        }
+    }
+
+    /// Checks if the schedule's time zone is still needed to tell when the digest is due.
+    pub(crate) fn needs_offset(&self) -> bool {
+        self.offset.is_none()
+    }
+
+    /// Takes the schedule's time zone from the offset of its events.
+    pub(crate) fn learn_offset(&mut self, events: &[Event]) {
+        if let Some(event) = events.first() {
+            self.offset = Some(*event.start.offset());
+        }
+    }
+
+    /// Returns the date to send the digest for if it is due, i.e. the digest time has passed today
+    /// and the digest has not yet been sent today, or `None` if it is not due or the schedule's
+    /// time zone is not yet known.
+    pub(crate) fn due(&self, now: DateTime<Utc>) -> Option<NaiveDate> {
+        let now = now.with_timezone(&self.offset?);
+        let today = now.date_naive();
+
+        (now.time() >= self.time && self.last_sent != Some(today)).then_some(today)
+    }
+
+    pub(crate) fn record_sent(&mut self, date: NaiveDate) {
+        self.last_sent = Some(date);
+    }
+}
+
+/// Builds the text of the digest of a day's events, e.g.
+/// "Today: Stg A 10:00 Opening, 19:00 Keynote; Stg B 11:00 Robots".
+///
+/// The headline events at each venue are listed, or the first event at each venue if there are no
+/// headline events that day. Venues are listed in rubric slot order. Events that do not fit within
+/// the maximum length are left out and counted at the end (e.g. "+3 more").
+pub(crate) fn build_digest(
+    events: &[Event],
+    date: NaiveDate,
+    news_settings: &NewsSettings,
+    tiers: &TierSettings,
+    max_length: usize,
+) -> Option<String> {
+    let todays_events: Vec<&Event> = events
+        .iter()
+        .filter(|event| event.start.date_naive() == date)
+        .collect();
+
+    let mut headline_events: Vec<&Event> = todays_events
+        .iter()
+        .copied()
+        .filter(|event| tiers.tier_for(event) == Tier::Headline)
+        .collect();
+
+    if headline_events.is_empty() {
+        let mut first_events = BTreeMap::<&str, &Event>::new();
+        for event in todays_events {
+            first_events
+                .entry(event.venue.as_str())
+                .and_modify(|first| {
+                    if event.start < first.start {
+                        *first = event;
+                    }
+                })
+                .or_insert(event);
+        }
+        headline_events = first_events.into_values().collect();
+    }
+
+    let mut venues = BTreeMap::<(i8, String), Vec<&Event>>::new();
+    for event in headline_events {
+        if let Some((short_name, number)) = news_settings.venue_details(&event.venue) {
+            venues.entry((number, short_name)).or_default().push(event);
+        }
+    }
+
+    let venues: Vec<String> = venues
+        .into_iter()
+        .map(|((_, short_name), mut events)| {
+            events.sort_by_key(|event| event.start);
+            let events: Vec<String> = events
+                .iter()
+                .map(|event| format!("{} {}", event.start.format("%H:%M"), event.title))
+                .collect();
+            format!("{short_name} {}", events.join(", "))
+        })
+        .collect();
+
+    if venues.is_empty() {
+        return None;
+    }
+
+    Some(fit_venues(&venues, max_length))
+}
+
+/// Joins as many of the venue summaries as fit within the maximum length, noting how many were
+/// left out.
+fn fit_venues(venues: &[String], max_length: usize) -> String {
+    let mut text = "Today:".to_string();
+
+    for (i, venue) in venues.iter().enumerate() {
+        let separator = if i == 0 { " " } else { "; " };
+        let candidate = format!("{text}{separator}{venue}");
+
+        let remaining = venues.len() - i - 1;
+        let more = if remaining > 0 {
+            format!(" +{remaining} more")
+        } else {
+            String::new()
+        };
+
+        if candidate.chars().count() + more.chars().count() > max_length {
+            // Always include part of the first venue rather than sending nothing useful
+            if i == 0 {
+                let available = max_length.saturating_sub(more.chars().count());
+                return format!(
+                    "{}{more}",
+                    message_length::shorten(&candidate, available, &[])
+                );
+            }
+
+            let left_out = venues.len() - i;
+            return format!("{text} +{left_out} more");
+        }
+
+        text = candidate;
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(start: &str) -> Event {
+        serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "start_date": start,
+            "end_date": start,
+            "venue": "Stage A",
+            "title": "Opening ceremony",
+            "speaker": "",
+            "description": "",
+            "type": "talk",
+        }))
+        .unwrap()
+    }
+
+    fn scheduler() -> DigestScheduler {
+        let mut scheduler = DigestScheduler::new("09:00:00".parse().unwrap());
+        scheduler.learn_offset(&[event("2024-05-30T10:00:00+01:00")]);
+        scheduler
+    }
+
+    #[test]
+    fn digest_is_not_due_until_the_time_zone_is_known() {
+        let scheduler = DigestScheduler::new("09:00:00".parse().unwrap());
+
+        assert!(scheduler.needs_offset());
+        assert_eq!(scheduler.due("2024-05-30T12:00:00Z".parse().unwrap()), None);
+    }
+
+    #[test]
+    fn digest_is_due_after_the_time_in_the_schedule_time_zone() {
+        let scheduler = scheduler();
+
+        // 08:30 in the schedule's time zone
+        assert_eq!(scheduler.due("2024-05-30T07:30:00Z".parse().unwrap()), None);
+
+        // 09:00 in the schedule's time zone
+        assert_eq!(
+            scheduler.due("2024-05-30T08:00:00Z".parse().unwrap()),
+            Some("2024-05-30".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn digest_is_due_once_a_day() {
+        let mut scheduler = scheduler();
+
+        scheduler.record_sent("2024-05-30".parse().unwrap());
+
+        assert_eq!(scheduler.due("2024-05-30T20:00:00Z".parse().unwrap()), None);
+
+        // The next day in the schedule's time zone, but before the digest time
+        assert_eq!(scheduler.due("2024-05-30T23:30:00Z".parse().unwrap()), None);
+
+        assert_eq!(
+            scheduler.due("2024-05-31T08:00:00Z".parse().unwrap()),
+            Some("2024-05-31".parse().unwrap())
+        );
+    }
+
+    fn venues(venues: &[&str]) -> Vec<String> {
+        venues.iter().map(|venue| venue.to_string()).collect()
+    }
+
+    #[test]
+    fn venues_that_fit_are_all_listed() {
+        let text = fit_venues(&venues(&["Stg A 10:00 Opening", "Stg B 11:00 Robots"]), 50);
+
+        assert_eq!(text, "Today: Stg A 10:00 Opening; Stg B 11:00 Robots");
+    }
+
+    #[test]
+    fn venues_that_do_not_fit_are_counted() {
+        let text = fit_venues(
+            &venues(&[
+                "Stg A 10:00 Opening",
+                "Stg B 11:00 Robots",
+                "Stg C 12:00 Lasers",
+            ]),
+            40,
+        );
+
+        assert_eq!(text, "Today: Stg A 10:00 Opening +2 more");
+        assert!(text.chars().count() <= 40);
+    }
+
+    #[test]
+    fn first_venue_is_shortened_rather_than_left_out() {
+        let text = fit_venues(
+            &venues(&[
+                "Stg A 10:00 Opening ceremony and welcome",
+                "Stg B 11:00 Robots",
+            ]),
+            30,
+        );
+
+        assert!(text.starts_with("Today: Stg A"), "{text}");
+        assert!(text.ends_with(" +1 more"), "{text}");
+        assert!(text.chars().count() <= 30, "{text}");
+    }
+}
@@ -0,0 +1,154 @@
+use metrics::counter;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use std::{
+    collections::HashMap,
+    future::Future,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tracing::warn;
+
+/// Resolves host names, reusing the addresses found for a while rather than looking them up for
+/// every connection, and falling back to the last addresses found if a lookup fails so that a DNS
+/// outage does not stop sending to hosts that were reachable before it.
+///
+/// Connections to the addresses are still made by the HTTP client, which tries IPv6 and IPv4
+/// addresses alongside each other (happy eyeballs).
+#[derive(Clone)]
+pub(crate) struct CachingResolver {
+    /// How long addresses are reused for before they are looked up again
+    ttl: Duration,
+
+    cache: Arc<Mutex<HashMap<String, CachedAddrs>>>,
+}
+
+struct CachedAddrs {
+    addrs: Vec<SocketAddr>,
+    resolved: Instant,
+}
+
+impl CachingResolver {
+    pub(crate) fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            cache: Arc::default(),
+        }
+    }
+
+    /// Resolves a host, using `lookup` to look it up when the cached addresses are missing or
+    /// too old.
+    async fn resolve_with<F, Fut>(&self, host: &str, lookup: F) -> std::io::Result<Vec<SocketAddr>>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = std::io::Result<Vec<SocketAddr>>>,
+    {
+        if let Some(cached) = self.cache.lock().unwrap().get(host) {
+            if cached.resolved.elapsed() < self.ttl {
+                return Ok(cached.addrs.clone());
+            }
+        }
+
+        match lookup().await {
+            Ok(addrs) if !addrs.is_empty() => {
+                self.cache.lock().unwrap().insert(
+                    host.to_string(),
+                    CachedAddrs {
+                        addrs: addrs.clone(),
+                        resolved: Instant::now(),
+                    },
+                );
+                Ok(addrs)
+            }
+            result => match self.cache.lock().unwrap().get(host) {
+                Some(cached) => {
+                    let reason = match result {
+                        Ok(_) => "no addresses found".to_string(),
+                        Err(e) => e.to_string(),
+                    };
+                    warn!(
+                        "Failed to look up {host} ({reason}), using addresses found {}s ago",
+                        cached.resolved.elapsed().as_secs()
+                    );
+                    counter!("dns_stale_lookups").increment(1);
+                    Ok(cached.addrs.clone())
+                }
+                None => result,
+            },
+        }
+    }
+}
+
+impl Resolve for CachingResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let resolver = self.clone();
+
+        Box::pin(async move {
+            let host = name.as_str();
+            let addrs = resolver
+                .resolve_with(host, || async {
+                    Ok(tokio::net::lookup_host((host, 0)).await?.collect())
+                })
+                .await?;
+
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addrs(addr: &str) -> Vec<SocketAddr> {
+        vec![addr.parse().unwrap()]
+    }
+
+    #[tokio::test]
+    async fn addresses_are_reused_until_they_expire() {
+        let resolver = CachingResolver::new(Duration::from_secs(60));
+
+        let first = resolver
+            .resolve_with("hampager.de", || async { Ok(addrs("192.0.2.1:0")) })
+            .await
+            .unwrap();
+        let second = resolver
+            .resolve_with("hampager.de", || async { Ok(addrs("192.0.2.2:0")) })
+            .await
+            .unwrap();
+
+        assert_eq!(first, addrs("192.0.2.1:0"));
+        assert_eq!(second, addrs("192.0.2.1:0"));
+    }
+
+    #[tokio::test]
+    async fn stale_addresses_are_used_when_a_lookup_fails() {
+        let resolver = CachingResolver::new(Duration::ZERO);
+
+        resolver
+            .resolve_with("hampager.de", || async { Ok(addrs("192.0.2.1:0")) })
+            .await
+            .unwrap();
+        let stale = resolver
+            .resolve_with("hampager.de", || async {
+                Err(std::io::Error::other("DNS is down"))
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(stale, addrs("192.0.2.1:0"));
+    }
+
+    #[tokio::test]
+    async fn lookup_failures_are_returned_when_nothing_is_cached() {
+        let resolver = CachingResolver::new(Duration::from_secs(60));
+
+        let result = resolver
+            .resolve_with("hampager.de", || async {
+                Err(std::io::Error::other("DNS is down"))
+            })
+            .await;
+
+        assert!(result.is_err());
+    }
+}
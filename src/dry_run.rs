@@ -0,0 +1,45 @@
+use crate::{call::PreparedCall, event_news::RubricNews};
+use std::{fs::OpenOptions, io::Write, path::Path};
+
+/// Appends news that would have been sent to a JSON lines file, so that the output of two dry
+/// runs can be compared.
+pub(crate) fn record_news(path: &Path, news: &RubricNews) -> anyhow::Result<()> {
+    append(
+        path,
+        serde_json::json!({
+            "type": "news",
+            "rubric": news.rubric,
+            "number": news.number,
+            "text": news.text,
+        }),
+    )
+}
+
+/// Appends a call that would have been sent to a JSON lines file, one line for each part of the
+/// call.
+pub(crate) fn record_call(
+    path: &Path,
+    call: &PreparedCall,
+    transmitter_groups: &[String],
+) -> anyhow::Result<()> {
+    for part in &call.parts {
+        append(
+            path,
+            serde_json::json!({
+                "type": "call",
+                "recipients": call.recipients,
+                "transmitter_groups": transmitter_groups,
+                "text": part,
+            }),
+        )?;
+    }
+
+    Ok(())
+}
+
+fn append(path: &Path, record: serde_json::Value) -> anyhow::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{record}")?;
+
+    Ok(())
+}
@@ -1,36 +1,348 @@
-use dapnet_api::{OutgoingNews, OutgoingNewsBuilder};
+use crate::{
+    clock::Clock, message_length, pocsag::CharsetSettings, rubric_slots::SlotAssignment,
+    template::MessageTemplate, venue_map::VenueMap,
+};
+use chrono::{DateTime, Duration, FixedOffset, Utc};
+use clap::ValueEnum;
+use dapnet_api::{OutgoingNews, OutgoingNewsBuilder, OutgoingNewsBuilderError};
 use emfcamp_schedule_api::schedule::event::Event;
-use tracing::error;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    sync::Arc,
+};
+use tracing::{info, warn};
+
+/// A news item for a rubric.
+///
+/// The fields of [`OutgoingNews`] cannot be read once it is built, so news is passed around as
+/// this and only converted when it is sent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RubricNews {
+    pub(crate) rubric: String,
+
+    /// News position (1-10), the first if not given
+    pub(crate) number: Option<i8>,
+
+    pub(crate) text: String,
+}
+
+impl RubricNews {
+    pub(crate) fn to_outgoing(&self) -> Result<OutgoingNews, OutgoingNewsBuilderError> {
+        OutgoingNewsBuilder::default()
+            .rubric(self.rubric.clone())
+            .number(self.number.unwrap_or(1))
+            .text(self.text.clone())
+            .build()
+    }
+}
+
+/// Settings controlling how news is built for events.
+pub(crate) struct NewsSettings {
+    /// Rubric to send news to
+    pub(crate) rubric: String,
+
+    /// Rubrics to send news for events of particular types to instead, keyed by event type
+    pub(crate) track_rubrics: HashMap<String, String>,
+
+    /// Whether to send news to the rubrics given for venues in the venue map
+    pub(crate) venue_rubrics: bool,
+
+    /// Automatically assigned rubric slots, the built in table is used if not set
+    pub(crate) slots: Option<SlotAssignment>,
+
+    /// Venue short names and news numbers, the built in table is used if not set
+    pub(crate) venue_map: Option<VenueMap>,
+
+    /// Maximum length of the news text
+    pub(crate) max_length: usize,
+
+    /// Append the first sentence of the event description, shortened to the room left for it
+    pub(crate) description_snippet: bool,
+
+    /// Include how long it is until the event starts (e.g. "in 2 min") in upcoming event news
+    pub(crate) relative_start_time: bool,
+
+    /// Templates for the text of upcoming and starting now news, replacing the built in formats
+    /// (see [`render_template`])
+    pub(crate) upcoming_template: Option<MessageTemplate>,
+    pub(crate) starting_now_template: Option<MessageTemplate>,
+
+    /// Template for the text of calls announcing events, calls use the news text if not set
+    pub(crate) call_template: Option<MessageTemplate>,
+
+    /// Offset of the schedule's clock from the real one
+    pub(crate) time_offset: Duration,
+
+    /// Clock used for times relative to now
+    pub(crate) clock: Arc<dyn Clock>,
+
+    /// How characters that cannot be sent via POCSAG are handled
+    pub(crate) charset: CharsetSettings,
+
+    /// Schedule fields that must never appear in news
+    pub(crate) redacted_fields: Vec<RedactedField>,
+}
+
+/// A schedule field that can be kept out of news for privacy reasons.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum RedactedField {
+    /// The name of the speaker
+    Speaker,
+    /// The event description
+    Description,
+}
+
+/// Which point in an event's life an announcement is made at.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub(crate) enum AnnouncementKind {
+    /// Shortly before the event starts
+    Upcoming,
+    /// As the event starts
+    StartingNow,
+}
+
+impl NewsSettings {
+    /// Every rubric news may be sent to.
+    pub(crate) fn rubrics(&self) -> BTreeSet<&str> {
+        std::iter::once(self.rubric.as_str())
+            .chain(self.track_rubrics.values().map(String::as_str))
+            .chain(
+                self.venue_map
+                    .iter()
+                    .filter(|_| self.venue_rubrics)
+                    .flat_map(|map| map.venues())
+                    .filter_map(|venue| venue.rubric.as_deref()),
+            )
+            .collect()
+    }
+
+    fn is_redacted(&self, field: RedactedField) -> bool {
+        self.redacted_fields.contains(&field)
+    }
+
+    /// Gets a copy of the event with any redacted fields blanked, which news is built from so
+    /// that their values cannot end up in it however the text is built.
+    fn redacted(&self, event: &Event) -> Event {
+        let mut event = event.clone();
+
+        for field in &self.redacted_fields {
+            match field {
+                RedactedField::Speaker => event.speaker.clear(),
+                RedactedField::Description => event.description.clear(),
+            }
+        }
+
+        event
+    }
+
+    /// Gets the short name and news number to announce a venue with, or `None` if it should not be
+    /// announced.
+    pub(crate) fn venue_details(&self, name: &str) -> Option<(String, i8)> {
+        let (short_name, number) = match self.venue_map.as_ref().and_then(|map| map.get(name)) {
+            Some(venue) => (venue.short_name.clone(), venue.number),
+            None => {
+                let venue = Venue::from_schedule_name(name);
+                (
+                    venue_short_name(venue.clone()),
+                    news_number_for_venue(&venue),
+                )
+            }
+        };
+
+        let number = match &self.slots {
+            Some(slots) => slots.slot_for_venue(name)?,
+            None => number,
+        };
+
+        Some((short_name, number))
+    }
+
+    /// Gets the rubric to send news for an event to, which is the rubric of its venue, then of its
+    /// type, if either is set.
+    fn rubric_for_event(&self, event: &Event) -> &str {
+        let venue_rubric = self
+            .venue_map
+            .as_ref()
+            .filter(|_| self.venue_rubrics)
+            .and_then(|map| map.get(&event.venue))
+            .and_then(|venue| venue.rubric.as_deref());
+
+        venue_rubric
+            .or_else(|| {
+                self.track_rubrics
+                    .get(&event_type(event))
+                    .map(String::as_str)
+            })
+            .unwrap_or(&self.rubric)
+    }
+}
+
+/// Gets the type of an event (e.g. "talk" or "workshop").
+pub(crate) fn event_type(event: &Event) -> String {
+    format!("{:?}", event.kind).to_lowercase()
+}
 
 pub(crate) trait EventExt {
-    fn to_rubric_news(&self) -> Option<OutgoingNews>;
+    fn to_rubric_news(&self, settings: &NewsSettings, kind: AnnouncementKind)
+        -> Option<RubricNews>;
+
+    /// Builds the text of a call announcing the event from the call template, or `None` if there
+    /// is no call template or the venue is not announced.
+    ///
+    /// The text is not shortened, as calls longer than the maximum length are handled when they
+    /// are sent.
+    fn to_call_text(&self, settings: &NewsSettings) -> Option<String>;
+
+    /// Builds a news item saying that the speaker of an event has changed.
+    fn to_speaker_change_news(&self, settings: &NewsSettings) -> Option<RubricNews>;
+
+    /// Builds a news item saying that an event has been cancelled.
+    fn to_cancellation_news(&self, settings: &NewsSettings) -> Option<RubricNews>;
+
+    /// Builds a news item saying that an event has moved from the given venue and/or start time,
+    /// sent to the slot of the venue it has moved from.
+    fn to_move_news(
+        &self,
+        settings: &NewsSettings,
+        previous_venue: &str,
+        previous_start: DateTime<FixedOffset>,
+    ) -> Option<RubricNews>;
 }
 
 impl EventExt for Event {
-    fn to_rubric_news(&self) -> Option<OutgoingNews> {
-        let venue = Venue::from_schedule_name(&self.venue);
+    fn to_rubric_news(
+        &self,
+        settings: &NewsSettings,
+        kind: AnnouncementKind,
+    ) -> Option<RubricNews> {
+        let event = &settings.redacted(self);
+        let (venue, news_number) = settings.venue_details(&event.venue)?;
+        let now = settings.clock.now() + settings.time_offset;
 
-        let news_number = news_number_for_venue(&venue);
-        let mut msg = format!("<{}> {}", venue_short_name(venue), self.title);
-        if msg.len() > 80 {
-            msg = format!("{}...", &msg[0..77]);
-        }
+        let template = match kind {
+            AnnouncementKind::Upcoming => &settings.upcoming_template,
+            AnnouncementKind::StartingNow => &settings.starting_now_template,
+        };
 
-        match OutgoingNewsBuilder::default()
-            .rubric("emfcamp".to_string())
-            .number(news_number)
-            .text(msg)
-            .build()
-        {
-            Ok(news) => Some(news),
-            Err(e) => {
-                error!("Failed to build news: {e}");
-                None
+        let rendered = template.as_ref().and_then(|template| {
+            render_template(template, event, &venue, now)
+                .inspect_err(|e| warn!("{e}, using the built in format instead"))
+                .ok()
+        });
+
+        let mut msg = match (kind, rendered) {
+            (_, Some(rendered)) => rendered,
+            (AnnouncementKind::Upcoming, None) if settings.relative_start_time => format!(
+                "<{venue}> {}: {}",
+                relative_time(event.start, now),
+                event.title
+            ),
+            (AnnouncementKind::Upcoming, None) => format!("<{venue}> {}", event.title),
+            (AnnouncementKind::StartingNow, None) => format!("<{venue}> Now: {}", event.title),
+        };
+
+        if settings.description_snippet {
+            if let Some(snippet) = first_sentence(&event.description) {
+                let remaining = settings
+                    .max_length
+                    .saturating_sub(format!("{msg} - ").chars().count());
+
+                // The snippet is shortened to whatever room is left, unless that would leave too
+                // little of it to be worth sending
+                if snippet.chars().count() <= remaining || remaining >= MIN_SNIPPET_LENGTH {
+                    msg = format!(
+                        "{msg} - {}",
+                        message_length::shorten(snippet, remaining, &[])
+                    );
+                }
             }
         }
+
+        build_news(event, settings, news_number, msg)
+    }
+
+    fn to_call_text(&self, settings: &NewsSettings) -> Option<String> {
+        let template = settings.call_template.as_ref()?;
+        let event = &settings.redacted(self);
+        let (venue, _) = settings.venue_details(&event.venue)?;
+        let now = settings.clock.now() + settings.time_offset;
+
+        render_template(template, event, &venue, now)
+            .inspect_err(|e| warn!("{e}, using the news text for calls instead"))
+            .ok()
+    }
+
+    fn to_speaker_change_news(&self, settings: &NewsSettings) -> Option<RubricNews> {
+        let (venue, news_number) = settings.venue_details(&self.venue)?;
+
+        let msg = if settings.is_redacted(RedactedField::Speaker) {
+            format!("<{venue}> {} has a new speaker", self.title)
+        } else {
+            format!("<{venue}> {} now presented by {}", self.title, self.speaker)
+        };
+
+        build_news(self, settings, news_number, msg)
+    }
+
+    fn to_cancellation_news(&self, settings: &NewsSettings) -> Option<RubricNews> {
+        let (venue, news_number) = settings.venue_details(&self.venue)?;
+
+        let msg = format!("<{venue}> CANCELLED: {}", self.title);
+
+        build_news(self, settings, news_number, msg)
+    }
+
+    fn to_move_news(
+        &self,
+        settings: &NewsSettings,
+        previous_venue: &str,
+        previous_start: DateTime<FixedOffset>,
+    ) -> Option<RubricNews> {
+        let (venue, news_number) = settings.venue_details(previous_venue)?;
+
+        let mut destination = Vec::new();
+        if self.venue != previous_venue {
+            destination.push(match settings.venue_details(&self.venue) {
+                Some((new_venue, _)) => new_venue,
+                None => self.venue.clone(),
+            });
+        }
+        if self.start != previous_start {
+            destination.push(self.start.format("%H:%M").to_string());
+        }
+
+        let msg = format!(
+            "<{venue}> MOVED to {}: {}",
+            destination.join(" "),
+            self.title
+        );
+
+        build_news(self, settings, news_number, msg)
     }
 }
 
+fn build_news(
+    event: &Event,
+    settings: &NewsSettings,
+    news_number: i8,
+    msg: String,
+) -> Option<RubricNews> {
+    let msg = settings.charset.sanitise(msg);
+
+    // The speaker is less important than the title, so is dropped first when news is too long
+    // (it is sanitised the same way as the news so that it can still be found in it)
+    let speaker = settings.charset.sanitise(event.speaker.clone());
+    let msg = message_length::shorten(&msg, settings.max_length, &[&speaker]);
+
+    Some(RubricNews {
+        rubric: settings.rubric_for_event(event).to_string(),
+        number: Some(news_number),
+        text: msg,
+    })
+}
+
+#[derive(Clone)]
 enum Venue {
     StageA,
     StageB,
@@ -47,23 +359,103 @@ enum Venue {
     Other(String),
 }
 
+/// Schedule names of the venues in the built in venue table.
+const KNOWN_VENUES: [(&str, Venue); 12] = [
+    ("Stage A", Venue::StageA),
+    ("Stage B", Venue::StageB),
+    ("Stage C", Venue::StageC),
+    ("Workshop 0 (Drop-in)", Venue::Workshop0),
+    ("Workshop 1 (NottingHack)", Venue::Workshop1),
+    ("Workshop 2 (Milliways)", Venue::Workshop2),
+    ("Workshop 3 (Furry High Commission)", Venue::Workshop3),
+    ("Workshop 4 (FieldFX)", Venue::Workshop4),
+    ("Workshop 5 (Maths)", Venue::Workshop5),
+    ("Workshop 6 (Hardware Hacking)", Venue::Workshop6),
+    ("Youth Workshop", Venue::YouthWorkshop),
+    ("Null Sector", Venue::NullSector),
+];
+
 impl Venue {
+    /// Looks up a venue by its schedule name, falling back to matching names loosely (see
+    /// [`normalise_venue_name`]) as venues tend to be renamed slightly between years.
     fn from_schedule_name(v: &str) -> Self {
-        match v {
-            "Stage A" => Self::StageA,
-            "Stage B" => Self::StageB,
-            "Stage C" => Self::StageC,
-            "Workshop 0 (Drop-in)" => Self::Workshop0,
-            "Workshop 1 (NottingHack)" => Self::Workshop1,
-            "Workshop 2 (Milliways)" => Self::Workshop2,
-            "Workshop 3 (Furry High Commission)" => Self::Workshop3,
-            "Workshop 4 (FieldFX)" => Self::Workshop4,
-            "Workshop 5 (Maths)" => Self::Workshop5,
-            "Workshop 6 (Hardware Hacking)" => Self::Workshop6,
-            "Youth Workshop" => Self::YouthWorkshop,
-            "Null Sector" => Self::NullSector,
-            other => Self::Other(other.to_string()),
-        }
+        let normalised = normalise_venue_name(v);
+
+        KNOWN_VENUES
+            .iter()
+            .find(|(name, _)| *name == v)
+            .or_else(|| {
+                KNOWN_VENUES
+                    .iter()
+                    .find(|(name, _)| normalise_venue_name(name) == normalised)
+            })
+            .map(|(_, venue)| venue.clone())
+            .unwrap_or_else(|| Self::Other(v.to_string()))
+    }
+}
+
+/// Lower cases a venue name, dropping any parenthesised suffix (e.g. a sponsor) and punctuation,
+/// so that "Workshop 1 (NottingHack)" and "workshop 1" are considered the same venue.
+pub(crate) fn normalise_venue_name(name: &str) -> String {
+    let name = match name.find('(') {
+        Some(i) => &name[..i],
+        None => name,
+    };
+
+    name.chars()
+        .filter(|c| c.is_alphanumeric() || c.is_whitespace())
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Checks if a schedule venue name is in the built in venue table.
+pub(crate) fn is_known_venue(name: &str) -> bool {
+    !matches!(Venue::from_schedule_name(name), Venue::Other(_))
+}
+
+/// Checks the built in venue table for venues that would be announced with the same short name,
+/// which is an error if `strict` is set and a warning otherwise.
+///
+/// Venues sharing a rubric slot are also reported, as this is sometimes intended (e.g. the
+/// workshops all share a slot).
+pub(crate) fn check_venue_table(strict: bool) -> anyhow::Result<()> {
+    let mut short_names = BTreeMap::<String, Vec<&str>>::new();
+    let mut slots = BTreeMap::<i8, Vec<&str>>::new();
+
+    for (name, venue) in &KNOWN_VENUES {
+        slots
+            .entry(news_number_for_venue(venue))
+            .or_default()
+            .push(*name);
+        short_names
+            .entry(venue_short_name(venue.clone()))
+            .or_default()
+            .push(*name);
+    }
+
+    for (slot, venues) in slots.iter().filter(|(_, venues)| venues.len() > 1) {
+        info!("Rubric slot {slot} is shared by {}", venues.join(", "));
+    }
+
+    let collisions: Vec<String> = short_names
+        .iter()
+        .filter(|(_, venues)| venues.len() > 1)
+        .map(|(short_name, venues)| format!("\"{short_name}\" ({})", venues.join(", ")))
+        .collect();
+
+    if collisions.is_empty() {
+        Ok(())
+    } else if strict {
+        Err(anyhow::anyhow!(
+            "Venues share short names: {}",
+            collisions.join("; ")
+        ))
+    } else {
+        warn!("Venues share short names: {}", collisions.join("; "));
+        Ok(())
     }
 }
 
@@ -103,3 +495,202 @@ fn venue_short_name(venue: Venue) -> String {
         Venue::Other(name) => name,
     }
 }
+
+/// Fields available in templates for the text of news and calls announcing events (see
+/// [`crate::template::EVENT_FIELDS`]).
+#[derive(Serialize)]
+struct EventFields<'a> {
+    /// Short name of the venue
+    venue: &'a str,
+    title: &'a str,
+    speaker: &'a str,
+    /// Event type (e.g. "talk")
+    #[serde(rename = "type")]
+    kind: String,
+    /// Start time (e.g. "14:30")
+    start: String,
+    /// Minutes until the event starts, rounded up
+    minutes: i64,
+    /// How long until the event starts (e.g. "in 2 min" or "now")
+    relative: String,
+}
+
+/// Renders a news or call template for an event.
+pub(crate) fn render_template(
+    template: &MessageTemplate,
+    event: &Event,
+    venue: &str,
+    now: DateTime<Utc>,
+) -> anyhow::Result<String> {
+    template.render(EventFields {
+        venue,
+        title: &event.title,
+        speaker: &event.speaker,
+        kind: event_type(event),
+        start: event.start.format("%H:%M").to_string(),
+        minutes: ((event.start.with_timezone(&Utc) - now).num_seconds().max(0) + 59) / 60,
+        relative: relative_time(event.start, now),
+    })
+}
+
+/// Describes how long it is until `start`, rounded up to the next minute.
+fn relative_time(start: DateTime<FixedOffset>, now: DateTime<Utc>) -> String {
+    let seconds = (start.with_timezone(&Utc) - now).num_seconds();
+
+    if seconds <= 0 {
+        "now".to_string()
+    } else {
+        format!("in {} min", (seconds + 59) / 60)
+    }
+}
+
+/// Shortest a description snippet is shortened to before it is left out altogether.
+const MIN_SNIPPET_LENGTH: usize = 12;
+
+fn first_sentence(text: &str) -> Option<&str> {
+    let text = text.trim();
+    let end = text
+        .find(['.', '!', '?', '\n'])
+        .map(|i| i + 1)
+        .unwrap_or(text.len());
+
+    let sentence = text[..end].trim();
+    (!sentence.is_empty()).then_some(sentence)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{clock::MockClock, pocsag::CharsetPolicy, template::EVENT_FIELDS};
+
+    fn event(title: &str, speaker: &str, description: &str) -> Event {
+        serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "start_date": "2024-05-30T14:00:00+01:00",
+            "end_date": "2024-05-30T15:00:00+01:00",
+            "venue": "Stage A",
+            "title": title,
+            "speaker": speaker,
+            "description": description,
+            "type": "talk",
+        }))
+        .unwrap()
+    }
+
+    fn settings(max_length: usize, template: Option<&str>) -> NewsSettings {
+        NewsSettings {
+            rubric: "emfcamp".to_string(),
+            track_rubrics: HashMap::new(),
+            venue_rubrics: false,
+            slots: None,
+            venue_map: None,
+            max_length,
+            description_snippet: false,
+            relative_start_time: false,
+            upcoming_template: template.map(|t| MessageTemplate::new(t, EVENT_FIELDS).unwrap()),
+            starting_now_template: None,
+            call_template: None,
+            time_offset: Duration::zero(),
+            clock: Arc::new(MockClock::new("2024-05-30T12:50:00Z".parse().unwrap())),
+            charset: CharsetSettings {
+                policy: CharsetPolicy::Warn,
+                transliterate: false,
+                replacements: Vec::new(),
+            },
+            redacted_fields: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn templates_are_rendered_for_events() {
+        let settings = settings(
+            80,
+            Some("{{ venue }} {{ start }} ({{ relative }}): {{ title }}"),
+        );
+
+        let news = event("Opening", "", "")
+            .to_rubric_news(&settings, AnnouncementKind::Upcoming)
+            .unwrap();
+
+        assert_eq!(news.text, "Stg A 14:00 (in 10 min): Opening");
+    }
+
+    #[test]
+    fn rendered_templates_are_kept_within_the_length_budget() {
+        let settings = settings(30, Some("{{ venue }}: {{ title }} by {{ speaker }}"));
+
+        let news = event("Soldering for beginners", "Jane Doe", "")
+            .to_rubric_news(&settings, AnnouncementKind::Upcoming)
+            .unwrap();
+
+        assert_eq!(news.text, "Stg A: Soldering for beginners");
+    }
+
+    #[test]
+    fn description_snippet_is_shortened_to_the_remaining_length() {
+        let mut settings = settings(40, None);
+        settings.description_snippet = true;
+        let event = event(
+            "Robots",
+            "",
+            "Build a robot from scrap parts. Bring a laptop.",
+        );
+
+        let news = event
+            .to_rubric_news(&settings, AnnouncementKind::Upcoming)
+            .unwrap();
+
+        assert_eq!(news.text, "<Stg A> Robots - Build a robot from...");
+        assert_eq!(news.text.chars().count(), 38);
+
+        settings.max_length = "<Stg A> Robots - Build a robot from scrap parts.".len();
+        let news = event
+            .to_rubric_news(&settings, AnnouncementKind::Upcoming)
+            .unwrap();
+
+        assert_eq!(
+            news.text,
+            "<Stg A> Robots - Build a robot from scrap parts."
+        );
+    }
+
+    #[test]
+    fn description_snippet_is_left_out_when_there_is_little_room() {
+        let mut settings = settings(28, None);
+        settings.description_snippet = true;
+        let event = event("Robots", "", "Build a robot from scrap parts.");
+
+        let news = event
+            .to_rubric_news(&settings, AnnouncementKind::Upcoming)
+            .unwrap();
+
+        assert_eq!(news.text, "<Stg A> Robots");
+    }
+
+    #[test]
+    fn venue_names_are_normalised() {
+        assert_eq!(
+            normalise_venue_name("Workshop 1 (NottingHack)"),
+            "workshop 1"
+        );
+        assert_eq!(normalise_venue_name("Workshop 1"), "workshop 1");
+        assert_eq!(normalise_venue_name("  Null-Sector  "), "nullsector");
+        assert_eq!(normalise_venue_name("Stage  A"), "stage a");
+    }
+
+    #[test]
+    fn venues_are_found_by_loosely_matching_names() {
+        assert!(matches!(
+            Venue::from_schedule_name("Workshop 1"),
+            Venue::Workshop1
+        ));
+        assert!(matches!(
+            Venue::from_schedule_name("workshop 1 (Hackspace)"),
+            Venue::Workshop1
+        ));
+        assert!(matches!(
+            Venue::from_schedule_name("Workshop 7"),
+            Venue::Other(name) if name == "Workshop 7"
+        ));
+    }
+}
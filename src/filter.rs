@@ -0,0 +1,211 @@
+use crate::event_news::{event_type, normalise_venue_name};
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use emfcamp_schedule_api::schedule::event::Event;
+use regex::Regex;
+
+/// Decides which events are announced.
+#[derive(Clone)]
+pub(crate) struct EventFilter {
+    pub(crate) min_duration: Option<Duration>,
+    pub(crate) max_duration: Option<Duration>,
+
+    /// Days of the week on which events are announced (all days if empty and no dates are given)
+    pub(crate) enabled_weekdays: Vec<Weekday>,
+
+    /// Dates on which events are announced (all days if empty and no weekdays are given)
+    pub(crate) enabled_dates: Vec<NaiveDate>,
+
+    /// Venues events are announced at (all venues if empty)
+    pub(crate) include_venues: Vec<String>,
+
+    /// Venues events are never announced at
+    pub(crate) exclude_venues: Vec<String>,
+
+    /// Types of event that are announced (all types if empty)
+    pub(crate) include_types: Vec<String>,
+
+    /// Events with titles matching this are not announced
+    pub(crate) exclude_title: Option<Regex>,
+}
+
+impl EventFilter {
+    /// Returns why the event should not be announced, or `None` if it should be.
+    pub(crate) fn rejection_reason(&self, event: &Event) -> Option<&'static str> {
+        let duration = event.end - event.start;
+
+        if self.min_duration.is_some_and(|min| duration < min) {
+            return Some("is shorter than the minimum duration");
+        }
+
+        if self.max_duration.is_some_and(|max| duration > max) {
+            return Some("is longer than the maximum duration");
+        }
+
+        if !self.is_enabled_day(event.start.date_naive()) {
+            return Some("is on a day announcements are not enabled on");
+        }
+
+        let venue = normalise_venue_name(&event.venue);
+
+        if !self.include_venues.is_empty()
+            && !self
+                .include_venues
+                .iter()
+                .any(|v| normalise_venue_name(v) == venue)
+        {
+            return Some("is not at an included venue");
+        }
+
+        if self
+            .exclude_venues
+            .iter()
+            .any(|v| normalise_venue_name(v) == venue)
+        {
+            return Some("is at an excluded venue");
+        }
+
+        if !self.include_types.is_empty() {
+            let event_type = event_type(event);
+            if !self
+                .include_types
+                .iter()
+                .any(|t| t.eq_ignore_ascii_case(&event_type))
+            {
+                return Some("is not of an included type");
+            }
+        }
+
+        if self
+            .exclude_title
+            .as_ref()
+            .is_some_and(|pattern| pattern.is_match(&event.title))
+        {
+            return Some("has a title matching the exclude pattern");
+        }
+
+        None
+    }
+
+    fn is_enabled_day(&self, date: NaiveDate) -> bool {
+        (self.enabled_weekdays.is_empty() && self.enabled_dates.is_empty())
+            || self.enabled_weekdays.contains(&date.weekday())
+            || self.enabled_dates.contains(&date)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(start: &str, end: &str, venue: &str, title: &str) -> Event {
+        serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "start_date": start,
+            "end_date": end,
+            "venue": venue,
+            "title": title,
+            "speaker": "",
+            "description": "",
+            "type": "workshop",
+        }))
+        .unwrap()
+    }
+
+    fn workshop() -> Event {
+        event(
+            "2024-05-30T14:00:00+01:00",
+            "2024-05-30T15:00:00+01:00",
+            "Workshop 1 (NottingHack)",
+            "Soldering for beginners",
+        )
+    }
+
+    fn filter() -> EventFilter {
+        EventFilter {
+            min_duration: None,
+            max_duration: None,
+            enabled_weekdays: Vec::new(),
+            enabled_dates: Vec::new(),
+            include_venues: Vec::new(),
+            exclude_venues: Vec::new(),
+            include_types: Vec::new(),
+            exclude_title: None,
+        }
+    }
+
+    #[test]
+    fn events_are_announced_by_default() {
+        assert_eq!(filter().rejection_reason(&workshop()), None);
+    }
+
+    #[test]
+    fn events_are_filtered_by_duration() {
+        let filter = EventFilter {
+            min_duration: Some(Duration::minutes(90)),
+            ..filter()
+        };
+
+        assert_eq!(
+            filter.rejection_reason(&workshop()),
+            Some("is shorter than the minimum duration")
+        );
+    }
+
+    #[test]
+    fn events_are_filtered_by_day() {
+        // 2024-05-30 is a Thursday
+        let filter = EventFilter {
+            enabled_weekdays: vec![Weekday::Fri],
+            ..filter()
+        };
+        assert_eq!(
+            filter.rejection_reason(&workshop()),
+            Some("is on a day announcements are not enabled on")
+        );
+
+        let filter = EventFilter {
+            enabled_dates: vec!["2024-05-30".parse().unwrap()],
+            ..filter
+        };
+        assert_eq!(filter.rejection_reason(&workshop()), None);
+    }
+
+    #[test]
+    fn venues_are_matched_loosely() {
+        let filter = EventFilter {
+            include_venues: vec!["workshop 1".to_string()],
+            ..filter()
+        };
+        assert_eq!(filter.rejection_reason(&workshop()), None);
+
+        let filter = EventFilter {
+            exclude_venues: vec!["Workshop 1".to_string()],
+            ..filter
+        };
+        assert_eq!(
+            filter.rejection_reason(&workshop()),
+            Some("is at an excluded venue")
+        );
+    }
+
+    #[test]
+    fn events_are_filtered_by_type_and_title() {
+        let by_type = EventFilter {
+            include_types: vec!["Talk".to_string()],
+            ..filter()
+        };
+        assert_eq!(
+            by_type.rejection_reason(&workshop()),
+            Some("is not of an included type")
+        );
+
+        let by_title = EventFilter {
+            exclude_title: Some(Regex::new("(?i)^soldering").unwrap()),
+            ..filter()
+        };
+        assert_eq!(
+            by_title.rejection_reason(&workshop()),
+            Some("has a title matching the exclude pattern")
+        );
+    }
+}
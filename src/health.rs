@@ -0,0 +1,99 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+#[derive(Default)]
+struct HealthState {
+    /// When the announcer last polled the schedule or sent anything
+    last_activity: Option<Instant>,
+
+    /// Whether the most recent schedule fetch succeeded, `None` before the first one
+    schedule_reachable: Option<bool>,
+
+    dapnet_authenticated: bool,
+}
+
+/// Tracks whether the announcer is working, for liveness and readiness probes.
+pub(crate) struct Health {
+    started: Instant,
+
+    /// How long the announcer may go without polling the schedule or sending anything before it is
+    /// considered stuck
+    stall_timeout: Duration,
+
+    state: Mutex<HealthState>,
+}
+
+impl Health {
+    pub(crate) fn new(stall_timeout: Duration) -> Self {
+        Self {
+            started: Instant::now(),
+            stall_timeout,
+            state: Mutex::new(HealthState::default()),
+        }
+    }
+
+    /// Records that the announcer polled the schedule or sent something, showing that it is still
+    /// working (rather than just that the main loop is still ticking).
+    pub(crate) fn record_activity(&self) {
+        self.state.lock().unwrap().last_activity = Some(Instant::now());
+    }
+
+    pub(crate) fn record_schedule_fetch(&self, ok: bool) {
+        self.state.lock().unwrap().schedule_reachable = Some(ok);
+    }
+
+    pub(crate) fn record_dapnet_authenticated(&self) {
+        self.state.lock().unwrap().dapnet_authenticated = true;
+    }
+
+    /// Checks that the announcer is still polling the schedule or sending, returning why not if it
+    /// is not.
+    pub(crate) fn liveness(&self) -> Result<(), String> {
+        let last_activity = self
+            .state
+            .lock()
+            .unwrap()
+            .last_activity
+            .unwrap_or(self.started);
+        let since = last_activity.elapsed();
+
+        if since > self.stall_timeout {
+            Err(format!(
+                "announcer has not polled the schedule or sent anything for {}s",
+                since.as_secs()
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Checks that the announcer is able to make announcements, returning every reason it cannot
+    /// if it is not.
+    pub(crate) fn readiness(&self) -> Result<(), Vec<String>> {
+        let mut problems = Vec::new();
+
+        if let Err(e) = self.liveness() {
+            problems.push(e);
+        }
+
+        let state = self.state.lock().unwrap();
+
+        match state.schedule_reachable {
+            Some(true) => {}
+            Some(false) => problems.push("schedule API is unreachable".to_string()),
+            None => problems.push("schedule has not been fetched yet".to_string()),
+        }
+
+        if !state.dapnet_authenticated {
+            problems.push("DAPNET authentication has not succeeded".to_string());
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+}
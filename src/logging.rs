@@ -0,0 +1,194 @@
+use chrono::Utc;
+use clap::ValueEnum;
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+use tracing_appender::{
+    non_blocking::WorkerGuard,
+    rolling::{RollingFileAppender, Rotation},
+};
+use tracing_subscriber::{filter::LevelFilter, fmt, layer::SubscriberExt, util::SubscriberInitExt};
+
+const FILE_PREFIX: &str = "emfcamp-dapnet-schedule-announcer";
+
+/// When a new log file is started.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub(crate) enum LogRotation {
+    Hourly,
+    Daily,
+    /// When the log file reaches the maximum size
+    Size,
+    Never,
+}
+
+/// Where log files are written and how they are rotated.
+pub(crate) struct LogFileSettings<'a> {
+    pub(crate) directory: &'a Path,
+    pub(crate) rotation: LogRotation,
+
+    /// Size in bytes a log file may grow to when rotating by size
+    pub(crate) max_size: u64,
+
+    /// Number of old log files to keep, all are kept if not set
+    pub(crate) max_files: Option<usize>,
+}
+
+/// Sets up logging to stdout and/or rotating log files.
+///
+/// The returned guard must be kept for as long as logs are written, dropping it flushes any
+/// buffered output to the log file.
+pub(crate) fn init(
+    stdout: bool,
+    file: Option<LogFileSettings>,
+) -> anyhow::Result<Option<WorkerGuard>> {
+    let (file_layer, guard) = match file {
+        Some(settings) => {
+            // tracing-appender only rotates by time, so rotating by size is done here
+            let file: Box<dyn Write + Send> = match settings.rotation {
+                LogRotation::Hourly => Box::new(rolling_appender(&settings, Rotation::HOURLY)?),
+                LogRotation::Daily => Box::new(rolling_appender(&settings, Rotation::DAILY)?),
+                LogRotation::Never => Box::new(rolling_appender(&settings, Rotation::NEVER)?),
+                LogRotation::Size => Box::new(SizeRotatingFile::new(
+                    settings.directory,
+                    settings.max_size,
+                    settings.max_files,
+                )?),
+            };
+
+            let (writer, guard) = tracing_appender::non_blocking(file);
+            (
+                Some(fmt::layer().with_writer(writer).with_ansi(false)),
+                Some(guard),
+            )
+        }
+        None => (None, None),
+    };
+
+    tracing_subscriber::registry()
+        .with(LevelFilter::INFO)
+        .with(stdout.then(fmt::layer))
+        .with(file_layer)
+        .init();
+
+    Ok(guard)
+}
+
+fn rolling_appender(
+    settings: &LogFileSettings,
+    rotation: Rotation,
+) -> anyhow::Result<RollingFileAppender> {
+    let mut appender = RollingFileAppender::builder()
+        .rotation(rotation)
+        .filename_prefix(FILE_PREFIX)
+        .filename_suffix("log");
+    if let Some(max_files) = settings.max_files {
+        appender = appender.max_log_files(max_files);
+    }
+
+    Ok(appender.build(settings.directory)?)
+}
+
+/// Log file that a new file is started in once it reaches a maximum size.
+///
+/// Logs are written to `<prefix>.log`, which is renamed with the time it was rotated at when it
+/// is full.
+struct SizeRotatingFile {
+    directory: PathBuf,
+    max_size: u64,
+    max_files: Option<usize>,
+    file: File,
+    size: u64,
+}
+
+impl SizeRotatingFile {
+    fn new(directory: &Path, max_size: u64, max_files: Option<usize>) -> io::Result<Self> {
+        std::fs::create_dir_all(directory)?;
+        let file = open_log_file(directory)?;
+        let size = file.metadata()?.len();
+
+        Ok(Self {
+            directory: directory.to_path_buf(),
+            max_size,
+            max_files,
+            file,
+            size,
+        })
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        let rotated = self.directory.join(format!(
+            "{FILE_PREFIX}.{}.log",
+            Utc::now().format("%Y-%m-%d-%H-%M-%S%.3f")
+        ));
+        std::fs::rename(log_file_path(&self.directory), rotated)?;
+        self.file = open_log_file(&self.directory)?;
+        self.size = 0;
+
+        match self.max_files {
+            Some(max_files) => self.remove_old_files(max_files),
+            None => Ok(()),
+        }
+    }
+
+    /// Removes the oldest rotated files so that at most `max_files` are kept.
+    fn remove_old_files(&self, max_files: usize) -> io::Result<()> {
+        let current = log_file_path(&self.directory);
+
+        let mut rotated: Vec<PathBuf> = std::fs::read_dir(&self.directory)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| *path != current)
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with(FILE_PREFIX) && name.ends_with(".log"))
+            })
+            .collect();
+
+        // Rotated files are named by the time they were rotated at, so this puts the oldest first
+        rotated.sort();
+
+        let excess = rotated.len().saturating_sub(max_files);
+        for path in &rotated[..excess] {
+            std::fs::remove_file(path)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Write for SizeRotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_size {
+            if let Err(e) = self.rotate() {
+                // Logging is not set up for this, and carrying on with the full file is better
+                // than losing logs
+                eprintln!("Failed to rotate log file: {e}");
+                self.size = 0;
+            }
+        }
+
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn log_file_path(directory: &Path) -> PathBuf {
+    directory.join(format!("{FILE_PREFIX}.log"))
+}
+
+fn open_log_file(directory: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_file_path(directory))
+}
@@ -1,22 +1,131 @@
+mod activity;
+mod adhoc;
+mod announce_event;
+mod announced;
+mod bench;
+mod build_info;
+mod call;
+mod clock;
+mod clock_check;
+mod connectivity;
+mod credentials;
+mod daemon;
+mod dapnet;
+mod dedup;
+mod digest;
+mod dns;
+mod dry_run;
 mod event_news;
+mod filter;
+mod health;
+mod logging;
+mod message_length;
+mod monitor;
+mod news_verify;
+mod notifier;
+mod now_next;
+mod observability;
+mod outage;
+mod overrides;
+mod plan;
+mod pocsag;
+mod profile;
+mod retry;
+mod rubric_slots;
+mod schedule_changes;
+mod schedule_source;
+mod sink_health;
+mod slot_occupancy;
+mod slot_rotation;
+mod startup;
+mod stats;
+mod suppress;
+mod template;
+mod tiers;
+mod venue_check;
+mod venue_map;
+mod watchdog;
 
-use crate::event_news::EventExt;
-use chrono::{Duration, Utc};
-use clap::Parser;
-use dapnet_api::{Client as DapnetClient, OutgoingCallBuilder};
+use crate::{
+    activity::{Activity, ActivityFeed},
+    adhoc::AdhocTarget,
+    announced::{AnnouncedEvents, AnnouncedState},
+    call::CallSettings,
+    clock::{Clock, MockClock, SystemClock},
+    credentials::CredentialSource,
+    dapnet::Dapnet,
+    dedup::DuplicateSuppressor,
+    digest::DigestScheduler,
+    dns::CachingResolver,
+    event_news::{AnnouncementKind, EventExt, NewsSettings, RedactedField, RubricNews},
+    filter::EventFilter,
+    health::Health,
+    logging::{LogFileSettings, LogRotation},
+    message_length::OverlongPolicy,
+    monitor::MonitorCommand,
+    notifier::{Announcement, CallNotifier, Notifier, RubricNotifier},
+    observability::{Authentication, Endpoints, Observability},
+    outage::OutageTracker,
+    overrides::ScheduleOverrides,
+    plan::AnnouncementPlan,
+    pocsag::{CharsetPolicy, CharsetSettings},
+    retry::RetryPolicy,
+    rubric_slots::{SlotAssignment, SlotOverflowPolicy},
+    schedule_changes::{ScheduleChangeTracker, ScheduleChanges},
+    schedule_source::ScheduleSource,
+    sink_health::SinkHealth,
+    slot_occupancy::SlotOccupancy,
+    slot_rotation::SlotRotation,
+    startup::{StartupPageSettings, StartupPageWhen},
+    template::MessageTemplate,
+    tiers::{Delivery, TierRule, TierSettings},
+    venue_map::VenueMap,
+    watchdog::AnnouncementWatchdog,
+};
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, NaiveTime, Utc, Weekday};
+use clap::{Parser, Subcommand};
 use emfcamp_schedule_api::{
     announcer::{Announcer, AnnouncerPollResult, AnnouncerSettingsBuilder},
+    schedule::event::Event,
     Client as ScheduleClient,
 };
-use metrics::{counter, describe_counter};
-use metrics_exporter_prometheus::PrometheusBuilder;
-use std::net::SocketAddr;
+use metrics::{counter, describe_counter, describe_gauge, gauge};
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use regex::Regex;
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    sync::mpsc::{self, UnboundedReceiver},
+};
 use tracing::{error, info, warn};
 use url::Url;
 
+const DEFAULT_PROFILE_FILE: &str = "profiles.toml";
+
 /// Announces the EMF schedule via DAPNET
 #[derive(Debug, Parser)]
+#[command(
+    version,
+    long_version = build_info::LONG_VERSION,
+    subcommand_negates_reqs = true
+)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Named profile to take defaults for other options from (e.g. emf2024), see --profile-file
+    #[arg(long, env)]
+    profile: Option<String>,
+
+    /// TOML file of named profiles, each a [name] table of options named as on the command line but with underscores (e.g. api_url = "...", dry_run = true), options given on the command line or in the environment take precedence
+    #[arg(long, env, default_value = DEFAULT_PROFILE_FILE)]
+    profile_file: PathBuf,
+
     /// Address of schedule API to source event data from
     #[arg(
         long,
@@ -25,130 +134,2866 @@ struct Cli {
     )]
     api_url: Url,
 
+    /// Proxy for all outgoing HTTP requests, either an HTTP(S) proxy (e.g. http://proxy:3128) or a SOCKS5 proxy (e.g. socks5h://proxy:1080, resolving host names via the proxy) (the HTTP_PROXY, HTTPS_PROXY and ALL_PROXY environment variables are also respected)
+    #[arg(long, env, value_parser = parse_proxy)]
+    proxy: Option<Url>,
+
     /// DAPNET username (user must have access to the emfcamp rubric)
-    #[arg(long, env)]
-    dapnet_username: String,
+    #[arg(long, env, required_unless_present = "dapnet_username_file")]
+    dapnet_username: Option<String>,
+
+    /// File containing the DAPNET username
+    #[arg(long, env, conflicts_with = "dapnet_username")]
+    dapnet_username_file: Option<PathBuf>,
 
     /// DAPNET password
-    #[arg(long, env)]
-    dapnet_password: String,
+    #[arg(
+        long,
+        env,
+        required_unless_present_any = ["dapnet_password_file", "dapnet_password_command"]
+    )]
+    dapnet_password: Option<String>,
+
+    /// File containing the DAPNET password
+    #[arg(long, env, conflicts_with = "dapnet_password")]
+    dapnet_password_file: Option<PathBuf>,
+
+    /// Command whose first line of output is the DAPNET password (e.g. "pass show dapnet"), run again if DAPNET rejects the password when sending news or a call
+    #[arg(long, env, conflicts_with_all = ["dapnet_password", "dapnet_password_file"])]
+    dapnet_password_command: Option<String>,
+
+    /// Shift the clock the schedule is compared against, e.g. -364days to rehearse last year's schedule as if it were happening now
+    #[arg(long, env, allow_hyphen_values = true, default_value = "0s", value_parser = parse_time_offset)]
+    time_offset: Duration,
 
     /// Time in seconds before the start time of an event to send the notification
     #[arg(long, env, default_value = "120")]
     pre_event_announcement_time: i64,
 
-    /// Do not send notifications for events (the start up check page is still sent)
+    /// Also announce events as they start, so the rubric shows what is on now
+    #[arg(long, env, default_value = "false")]
+    starting_now_announcements: bool,
+
+    /// Minimum duration in minutes of events to announce as they start
+    #[arg(long, env)]
+    starting_now_min_duration: Option<i64>,
+
+    /// Maximum duration in minutes of events to announce as they start
+    #[arg(long, env)]
+    starting_now_max_duration: Option<i64>,
+
+    /// Send news when the speaker of an already announced event changes
+    #[arg(long, env, default_value = "false")]
+    announce_speaker_changes: bool,
+
+    /// Time in seconds between checks of the schedule for changes to already announced events
+    #[arg(long, env, default_value = "300")]
+    schedule_check_interval: u64,
+
+    /// Page the operator with a summary of how the schedule changed (e.g. "Sched upd: +2 -1 moved 3") whenever it is checked and has changed
+    #[arg(long, env, default_value = "false")]
+    page_schedule_changes: bool,
+
+    /// Send news when an event that has been announced, or starts within the schedule change horizon, is cancelled or moves time or venue
+    #[arg(long, env, default_value = "false")]
+    announce_schedule_changes: bool,
+
+    /// Time in minutes ahead of now within which cancelled and moved events are announced even if they have not been announced yet
+    #[arg(long, env, default_value = "60")]
+    schedule_change_horizon: i64,
+
+    /// Assign rubric slots to the venues found in the schedule at startup instead of using the built in table
+    #[arg(long, env, default_value = "false")]
+    auto_rubric_slots: bool,
+
+    /// Fail at startup if venues in the venue table share a short name, instead of warning about it
+    #[arg(long, env, default_value = "false")]
+    strict_venue_table: bool,
+
+    /// Venues in priority order for automatic rubric slot assignment (other venues are assigned alphabetically after these), venue groups can be given as @name
+    #[arg(long, env, value_delimiter = ',')]
+    venue_priority: Vec<String>,
+
+    /// What to do with venues that do not get their own rubric slot during automatic assignment
+    #[arg(long, env, value_enum, default_value_t = SlotOverflowPolicy::Share)]
+    slot_overflow_policy: SlotOverflowPolicy,
+
+    /// Minimum duration in minutes of events to announce
+    #[arg(long, env)]
+    min_duration: Option<i64>,
+
+    /// Maximum duration in minutes of events to announce
+    #[arg(long, env)]
+    max_duration: Option<i64>,
+
+    /// Days of the week on which events are announced, e.g. Fri,Sat,Sun (every day if neither this or enabled dates are set)
+    #[arg(long, env, value_delimiter = ',')]
+    enabled_weekdays: Vec<Weekday>,
+
+    /// Dates on which events are announced, e.g. 2024-05-31 (every day if neither this or enabled weekdays are set)
+    #[arg(long, env, value_delimiter = ',')]
+    enabled_dates: Vec<NaiveDate>,
+
+    /// Venues events are announced at, e.g. "Stage A,Stage B" or "@stages" for a venue group (every venue if not set)
+    #[arg(long, env, value_delimiter = ',')]
+    include_venue: Vec<String>,
+
+    /// Venues events are never announced at, venue groups can be given as @name
+    #[arg(long, env, value_delimiter = ',')]
+    exclude_venue: Vec<String>,
+
+    /// Types of event that are announced, e.g. talk,performance (every type if not set)
+    #[arg(long, env, value_delimiter = ',')]
+    include_type: Vec<String>,
+
+    /// Do not announce events with titles matching this regular expression, e.g. "(?i)drop-in"
+    #[arg(long, env)]
+    exclude_title_regex: Option<Regex>,
+
+    /// Maximum length of news text, longer messages are truncated
+    #[arg(long, env, default_value = "80")]
+    max_news_length: usize,
+
+    /// Append the first sentence of the event description to news, shortened to fit within the maximum length
+    #[arg(long, env, default_value = "false")]
+    description_snippet: bool,
+
+    /// Include how long it is until the event starts in news (e.g. "in 2 min"), based on the time the news is actually sent
+    #[arg(long, env, default_value = "false")]
+    relative_start_time: bool,
+
+    /// Template for the text of upcoming event news, replacing the built in format, in minijinja syntax with fields venue, title, speaker, type, start, minutes and relative (e.g. "{{ venue }} {{ start }}: {{ title }}")
+    #[arg(long, env, value_parser = parse_event_template)]
+    message_template: Option<MessageTemplate>,
+
+    /// Template for the text of starting now news, with the same fields as --message-template
+    #[arg(long, env, value_parser = parse_event_template)]
+    starting_now_message_template: Option<MessageTemplate>,
+
+    /// Template for the text of calls announcing events (to the tier call recipients, call targets and venue call recipients), with the same fields as --message-template (calls use the news text if not set)
+    #[arg(long, env, value_parser = parse_event_template)]
+    call_message_template: Option<MessageTemplate>,
+
+    /// Do not overwrite a rubric slot holding news for an event that starts sooner than the one being announced
+    #[arg(long, env, default_value = "false")]
+    protect_imminent_news: bool,
+
+    /// Time in seconds within which an identical message to the same target (rubric slot or call recipients) is not sent again (disabled if not set)
+    #[arg(long, env)]
+    duplicate_suppression_window: Option<i64>,
+
+    /// Time in seconds between cycling the content of a rubric slot shared by several events that have not yet started (disabled if not set)
+    #[arg(long, env)]
+    slot_rotation_interval: Option<u64>,
+
+    /// What to do with characters in news and calls that cannot be sent via POCSAG
+    #[arg(long, env, value_enum, default_value_t = CharsetPolicy::Warn)]
+    charset_policy: CharsetPolicy,
+
+    /// Replace accented characters, curly quotes, emoji, etc. with their closest ASCII equivalent before applying the charset policy
+    #[arg(long, env)]
+    transliterate: bool,
+
+    /// Text to replace in news and calls before transliterating, in the form from=to (e.g. "€=EUR"), may be given multiple times
+    #[arg(long, env, value_parser = parse_char_replacement)]
+    char_replacement: Vec<(String, String)>,
+
+    /// Schedule fields to keep out of all news and calls, for privacy reasons (they are treated as empty, e.g. the speaker field in a template is left blank)
+    #[arg(long, env, value_enum, value_delimiter = ',')]
+    redact_field: Vec<RedactedField>,
+
+    /// TOML file of venue short names and news numbers to use instead of the built in venue table, with a [[venue]] table for each venue giving its name, short_name and number, and optionally a rubric and call_recipients to route its announcements to (venues not in the file fall back to the built in table), and a [[group]] table for each venue group giving its name, venues and optionally call_recipients
+    #[arg(long, env)]
+    venue_map: Option<PathBuf>,
+
+    /// Send news for events of a type to a rubric other than the emfcamp rubric, e.g. workshop=emfcamp-workshops (ignored in shadow mode)
+    #[arg(long, env, value_delimiter = ',', value_parser = parse_track_rubric)]
+    track_rubric: Vec<(String, String)>,
+
+    /// Send news to this rubric instead of the emfcamp rubric, for end to end testing without paging subscribers (the "now & next" summaries are also sent here, and calls announcing events go to --shadow-call-recipients)
+    #[arg(long, env)]
+    shadow_rubric: Option<String>,
+
+    /// Recipients of every call announcing an event (to tier recipients, call targets and venue call recipients) in shadow mode, no such calls are made in shadow mode if not set
+    #[arg(long, env, value_delimiter = ',', requires = "shadow_rubric")]
+    shadow_call_recipients: Vec<String>,
+
+    /// Rules placing events into priority tiers, in the form tier=venue:<name>, tier=venue:@<venue group>, tier=type:<event type> or tier=event:<id> (the first matching rule is used, events matching none are in the normal tier)
+    #[arg(long, env, value_delimiter = ',', value_parser = tiers::parse_tier_rule)]
+    event_tier: Vec<TierRule>,
+
+    /// How events in the headline tier are announced
+    #[arg(long, env, value_enum, default_value_t = Delivery::News)]
+    headline_delivery: Delivery,
+
+    /// How events in the normal tier are announced
+    #[arg(long, env, value_enum, default_value_t = Delivery::News)]
+    normal_delivery: Delivery,
+
+    /// How events in the low tier are announced
+    #[arg(long, env, value_enum, default_value_t = Delivery::News)]
+    low_delivery: Delivery,
+
+    /// Recipients of calls for events in tiers that are announced by call
+    #[arg(long, env, value_delimiter = ',')]
+    tier_call_recipients: Vec<String>,
+
+    /// Transmitter groups calls for events in tiers that are announced by call, and calls to call targets, are sent via (the transmitter groups if not set)
+    #[arg(long, env, value_delimiter = ',')]
+    tier_call_transmitter_groups: Vec<String>,
+
+    /// Additional recipients to send every announced event to as a call, in the form name=CALLSIGN+CALLSIGN (the name labels metrics)
+    #[arg(long, env, value_delimiter = ',', value_parser = notifier::parse_call_target)]
+    call_target: Vec<(String, Vec<String>)>,
+
+    /// Callsign to send a copy of every announcement to, as live verification that announcements are going out
+    #[arg(long, env)]
+    canary_callsign: Option<String>,
+
+    /// Do not send anything, only log (and record to the dry run output) what would have been sent (the start up check page is still sent)
     #[arg(long, env, default_value = "false")]
     dry_run: bool,
 
-    /// Address on which to run the metrics endpoint
-    #[arg(long, env, default_value = "127.0.0.1:9090")]
-    observability_address: SocketAddr,
+    /// Directory (or single file) that other systems can drop JSON ad-hoc announcements into, e.g. {"text": "...", "target": {"type": "news", "number": 9}, "send_at": "2024-05-31T12:00:00Z"}
+    #[arg(long, env)]
+    adhoc_announcements: Option<PathBuf>,
+
+    /// Time in seconds between checks for ad-hoc announcements
+    #[arg(long, env, default_value = "10")]
+    adhoc_poll_interval: u64,
+
+    /// File listing IDs of events that are never announced, one per line, re-read before each announcement
+    #[arg(long, env)]
+    suppressed_events_file: Option<PathBuf>,
+
+    /// JSON file of local corrections to events keyed by event ID (e.g. {"123": {"title": "...", "venue": "...", "start": "...", "end": "...", "hidden": true}}), applied wherever the schedule is used and re-read each time it is fetched
+    #[arg(long, env)]
+    schedule_overrides: Option<PathBuf>,
+
+    /// File to append every news item and call that would have been sent to as JSON lines when in dry run mode
+    #[arg(long, env)]
+    dry_run_output: Option<PathBuf>,
+
+    /// DAPNET cores to use, e.g. the public core and one on site, news and calls are sent to every one of them (pagers ignore the duplicates) so that they go out if any core is reachable
+    #[arg(long, env, value_delimiter = ',', default_value = dapnet::API_URL)]
+    dapnet_url: Vec<Url>,
+
+    /// Time in seconds to wait for DAPNET to accept news or a call before treating it as failed
+    #[arg(long, env, default_value = "30")]
+    dapnet_timeout: u64,
+
+    /// Time in seconds to reuse the addresses of DAPNET cores for before looking them up again, the last addresses found are used for as long as lookups fail
+    #[arg(long, env, default_value = "60")]
+    dns_cache_ttl: u64,
+
+    /// Time in seconds to wait for a connection to DAPNET (including the TLS handshake) to be made before treating the request as failed
+    #[arg(long, env, default_value = "10")]
+    dapnet_connect_timeout: u64,
+
+    /// Time in seconds to wait for DAPNET to send any of its response before treating the request as failed
+    #[arg(long, env, default_value = "30")]
+    dapnet_read_timeout: u64,
+
+    /// Number of times news or a call is attempted before giving up, including the first attempt
+    #[arg(long, env, default_value = "5")]
+    dapnet_retry_attempts: u32,
+
+    /// Time in milliseconds to wait before the first retry, doubling for each retry after that
+    #[arg(long, env, default_value = "1000")]
+    dapnet_retry_initial_delay: u64,
+
+    /// Longest time in seconds to spend sending news or a call (across every part and recipient of a call), after which it is no longer retried
+    #[arg(long, env, default_value = "120")]
+    dapnet_retry_max_duration: u64,
+
+    /// Address ("host:port") of DAPNET to check can be connected to before sending news, news is not sent if it cannot be (not checked if not set)
+    #[arg(long, env)]
+    dapnet_connectivity_check: Option<String>,
+
+    /// Number of consecutive failures sending news after which sending is paused (disabled if not set)
+    #[arg(long, env)]
+    dapnet_circuit_breaker_threshold: Option<usize>,
+
+    /// Time in seconds to pause sending news for once the circuit breaker has opened
+    #[arg(long, env, default_value = "300")]
+    dapnet_circuit_breaker_cooldown: i64,
+
+    /// Read back the rubric after sending news to check that it was not overwritten by another client
+    #[arg(long, env, default_value = "false")]
+    verify_news: bool,
+
+    /// Number of times to resend news that fails verification
+    #[arg(long, env, default_value = "1")]
+    verify_news_retries: usize,
+
+    /// Callsign of the operator, who is sent alert pages
+    #[arg(long, env, default_value = "m0nxn")]
+    operator_callsign: String,
+
+    /// When to send the startup check page
+    #[arg(long, env, value_enum, default_value_t = StartupPageWhen::Always)]
+    startup_page: StartupPageWhen,
+
+    /// Stop if the startup check page cannot be sent, instead of carrying on without it
+    #[arg(long, env, default_value = "false")]
+    require_dapnet_check: bool,
+
+    /// Template for the text of the startup check page, in minijinja syntax with fields time, version, git_hash, mode and event_count (the number of events today)
+    #[arg(
+        long,
+        env,
+        value_parser = parse_startup_page_template,
+        default_value = "M0NXN: EMF sched. anncr. start at {{ time }}"
+    )]
+    startup_page_template: MessageTemplate,
+
+    /// Recipients of the startup pages (the operator if not set)
+    #[arg(long, env, value_delimiter = ',')]
+    startup_page_recipients: Vec<String>,
+
+    /// Transmitter groups the startup and shutdown pages are sent via (the transmitter groups if not set)
+    #[arg(long, env, value_delimiter = ',')]
+    startup_page_transmitter_groups: Vec<String>,
+
+    /// Time in seconds to wait for the startup check page to be sent before carrying on without it
+    #[arg(long, env, default_value = "30")]
+    startup_page_timeout: u64,
+
+    /// Send the startup check page in the background instead of waiting for it before polling the schedule
+    #[arg(long, env, default_value = "false")]
+    startup_page_background: bool,
+
+    /// Send a page to the startup page recipients when stopping on ctrl-c, SIGTERM or from the monitor
+    #[arg(long, env, default_value = "false")]
+    shutdown_page: bool,
+
+    /// Template for the text of the shutdown page, in minijinja syntax with fields time and reason (which includes how many queued news and calls were not sent, if any)
+    #[arg(
+        long,
+        env,
+        value_parser = parse_shutdown_page_template,
+        default_value = "M0NXN: EMF sched. anncr. stop at {{ time }} ({{ reason }})"
+    )]
+    shutdown_page_template: MessageTemplate,
+
+    /// Time in seconds that schedule fetches must fail for before paging the operator (disabled if not set)
+    #[arg(long, env)]
+    schedule_outage_alert_time: Option<i64>,
+
+    /// Time in the schedule's time zone to send a digest of the day's headline events at each venue, e.g. 09:00 (disabled if not set)
+    #[arg(long, env)]
+    digest_time: Option<NaiveTime>,
+
+    /// Rubric slot (1 to 10) to send the daily digest to
+    #[arg(long, env, default_value = "10")]
+    digest_news_number: i8,
+
+    /// Rubric to periodically send a "now & next" summary of the current and next event at each venue to (disabled if not set)
+    #[arg(long, env)]
+    now_next_rubric: Option<String>,
+
+    /// Time in minutes between "now & next" summaries
+    #[arg(long, env, default_value = "10")]
+    now_next_interval: u64,
+
+    /// File to record announced events in, so that they are not announced again after a restart
+    #[arg(long, env)]
+    state_file: Option<PathBuf>,
+
+    /// Callsign to periodically send a heartbeat page to (disabled if not set)
+    #[arg(long, env)]
+    heartbeat_callsign: Option<String>,
+
+    /// Time in hours between heartbeat pages
+    #[arg(long, env, default_value = "6")]
+    heartbeat_interval: u64,
+
+    /// Time in hours without a successful announcement (while events were due) before paging the operator (disabled if not set)
+    #[arg(long, env)]
+    announcement_watchdog_time: Option<i64>,
+
+    /// Time in seconds after an announcement was due before it is flagged as overdue if it has not been attempted
+    #[arg(long, env, default_value = "120")]
+    announcement_overdue_grace: i64,
+
+    /// Dates (UTC) on which the announcement watchdog is active, e.g. 2024-05-30 (every day if not set)
+    #[arg(long, env, value_delimiter = ',')]
+    event_days: Vec<NaiveDate>,
+
+    /// Send a second page at startup summarising today's events (doubles as a check that the schedule can be fetched), only sent when the startup page is and never in a dry run
+    #[arg(long, env, default_value = "false")]
+    startup_summary_page: bool,
+
+    /// Difference in seconds between the local clock and the schedule API above which a warning is logged
+    #[arg(long, env, default_value = "5")]
+    clock_skew_threshold: i64,
+
+    /// Time in seconds between checks of the local clock against the schedule API
+    #[arg(long, env, default_value = "3600")]
+    clock_check_interval: u64,
+
+    /// Maximum number of recipients in a single DAPNET call, larger recipient lists are split into several calls
+    #[arg(long, env, default_value = "20")]
+    max_recipients_per_call: usize,
+
+    /// Transmitter groups all calls are sent via unless more specific ones are given, e.g. only the on-site transmitter, may be given multiple times
+    #[arg(long, env, value_delimiter = ',', default_value = "uk-all")]
+    transmitter_group: Vec<String>,
+
+    /// Maximum length of the text of a call
+    #[arg(long, env, default_value = "80")]
+    max_call_length: usize,
+
+    /// What to do with calls longer than the maximum length
+    #[arg(long, env, value_enum, default_value_t = OverlongPolicy::Truncate)]
+    overlong_calls: OverlongPolicy,
+
+    /// Callsigns that may be sent calls, if set any other recipient is removed from outgoing calls
+    #[arg(long, env, value_delimiter = ',')]
+    recipient_allowlist: Vec<String>,
+
+    /// Callsigns that are removed from all outgoing calls
+    #[arg(long, env, value_delimiter = ',')]
+    recipient_blocklist: Vec<String>,
+
+    /// URL to send a GET request to after each successful poll of the announcer and each news item or call sent (e.g. a healthchecks.io ping URL)
+    #[arg(long, env)]
+    healthcheck_ping_url: Option<Url>,
+
+    /// Addresses on which to run the observability endpoints (metrics at /metrics, live activity WebSocket at /activity, sent announcements as server-sent events at /announcements, announcement plan as iCalendar at /plan.ics, liveness and readiness probes at /healthz and /readyz), all of which are read only (there are no admin endpoints)
+    #[arg(long, env, value_delimiter = ',', default_value = "127.0.0.1:9090")]
+    observability_address: Vec<SocketAddr>,
+
+    /// Additional addresses on which to serve only the metrics endpoint and health probes, e.g. to let a Prometheus server scrape metrics without exposing the live activity
+    #[arg(long, env, value_delimiter = ',')]
+    metrics_address: Vec<SocketAddr>,
+
+    /// Additional addresses on which to serve only the activity, announcements and announcement plan endpoints and health probes
+    #[arg(long, env, value_delimiter = ',')]
+    status_address: Vec<SocketAddr>,
+
+    /// Time in seconds the announcer may go without polling the schedule or sending anything before /healthz reports a failure
+    #[arg(long, env, default_value = "300")]
+    health_stall_timeout: u64,
+
+    /// Path of a Unix domain socket on which to run the observability endpoints instead of the TCP addresses
+    #[arg(long, env)]
+    observability_socket: Option<PathBuf>,
+
+    /// PEM certificate chain to serve the observability endpoints over HTTPS with, so that status and any observability credentials are not sent in plain text (TCP addresses only)
+    #[arg(long, env, requires = "observability_tls_key")]
+    observability_tls_cert: Option<PathBuf>,
+
+    /// PEM private key for the observability TLS certificate
+    #[arg(long, env, requires = "observability_tls_cert")]
+    observability_tls_key: Option<PathBuf>,
+
+    /// Token clients must present as a bearer token to access the observability endpoints
+    #[arg(long, env, conflicts_with = "observability_basic_auth")]
+    observability_bearer_token: Option<String>,
+
+    /// Credentials clients must present via basic auth to access the observability endpoints (in the form "username:password")
+    #[arg(long, env)]
+    observability_basic_auth: Option<String>,
+
+    /// Directory to write log files to, in addition to stdout
+    #[arg(long, env)]
+    log_directory: Option<PathBuf>,
+
+    /// When to start a new log file
+    #[arg(long, env, value_enum, default_value_t = LogRotation::Daily)]
+    log_rotation: LogRotation,
+
+    /// Size in MiB a log file may grow to before a new one is started, when rotating by size
+    #[arg(long, env, default_value = "10")]
+    log_max_size: u64,
+
+    /// Number of old log files to keep (all are kept if not set)
+    #[arg(long, env, requires = "log_directory")]
+    log_max_files: Option<usize>,
+
+    /// Detach from the terminal and run in the background, for hosts without a service manager
+    #[arg(long, env, default_value = "false")]
+    daemonise: bool,
+
+    /// File to write the process ID to when running in the background
+    #[arg(long, env, requires = "daemonise")]
+    pid_file: Option<PathBuf>,
+
+    /// File to append log output to when running in the background (otherwise it is discarded)
+    #[arg(long, env, requires = "daemonise")]
+    log_file: Option<PathBuf>,
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    let cli = Cli::parse();
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Print the announcement for a single event, optionally sending it (e.g. to resend one that failed or was corrected)
+    AnnounceEvent {
+        /// ID of the event in the schedule
+        id: u32,
 
-    tracing_subscriber::fmt::init();
+        /// Which announcement to make
+        #[arg(long, value_enum, default_value_t = AnnouncementKind::Upcoming)]
+        kind: AnnouncementKind,
 
-    // Set up metrics server
-    let builder = PrometheusBuilder::new();
-    builder
-        .with_http_listener(cli.observability_address)
-        .install()?;
+        /// Send the announcement to DAPNET rather than only printing it
+        #[arg(long)]
+        send: bool,
+    },
 
-    describe_counter!(
-        "dapnet_event_announcements",
-        "Number of announcements sent to DAPNET"
-    );
+    /// Handle every event in a schedule file as the announcer would (without sending anything) as fast as possible, reporting throughput and how many events were announced
+    Bench {
+        /// Schedule JSON file (e.g. a previous year's schedule export)
+        schedule: PathBuf,
 
-    // Setup schedule API client
-    let schedule_client = ScheduleClient::new(cli.api_url);
+        /// Number of times to process the schedule
+        #[arg(long, default_value = "1")]
+        iterations: usize,
+    },
 
-    let event_start_offset = -Duration::try_seconds(cli.pre_event_announcement_time)
-        .ok_or_else(|| anyhow::anyhow!("Invalid pre event announcement time"))?;
-    info!("Event start offset: {:?}", event_start_offset);
+    /// List every venue in the schedule and how it is announced (using --venue-map if given), flagging those missing from the venue table (an error with --strict-venue-table)
+    CheckVenues,
 
-    let mut announcer = Announcer::new(
-        AnnouncerSettingsBuilder::default()
-            .event_start_offset(event_start_offset)
-            .build()?,
-        schedule_client,
-    )
-    .await?;
+    /// Run the announcer with a terminal UI showing pending announcements, recent sends and DAPNET health, and a prompt for sending news by hand
+    Monitor,
 
-    // Setup and test DAPNET client
-    let dapnet = DapnetClient::new(&cli.dapnet_username, &cli.dapnet_password);
-    send_startup_page(&dapnet).await?;
+    /// Print every announcement that will be made for the schedule
+    Plan,
 
-    loop {
-        tokio::select! {
-            _ = tokio::signal::ctrl_c() => {
-                return Ok(());
-            }
-            msg = announcer.poll() => {
-                handle_announcer_event(&dapnet, cli.dry_run, msg).await;
-            }
-        }
+    /// Summarise the schedule by venue, day and hour, and find the most events running at once
+    Stats,
+}
+
+fn main() -> anyhow::Result<()> {
+    let cli: Cli = profile::parse(std::env::args_os().collect())?;
+
+    if cli.daemonise {
+        daemon::daemonise(cli.pid_file.as_deref(), cli.log_file.as_deref())?;
     }
+
+    // The DAPNET client is given the proxy directly, but the schedule API crate creates its own
+    // HTTP clients, which can only pick the proxy up from the environment. This must be set before
+    // the runtime starts any threads that could read it.
+    if let Some(proxy) = &cli.proxy {
+        std::env::set_var("ALL_PROXY", proxy.as_str());
+    }
+
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?
+        .block_on(async_main(cli))
 }
 
-async fn handle_announcer_event(
-    dapnet: &DapnetClient,
-    dry_run: bool,
-    msg: emfcamp_schedule_api::Result<AnnouncerPollResult>,
-) {
-    match msg {
-        Ok(AnnouncerPollResult::Event(event)) => {
-            if let Some(news) = event.to_rubric_news() {
-                info!("News for event: {:?}", news);
-
-                if !dry_run {
-                    match dapnet.new_news(&news).await {
-                        Ok(_) => {
-                            info!("News sent");
-                            counter!("dapnet_event_announcements", "result" => "ok").increment(1);
-                        }
-                        Err(e) => {
-                            error!("Failed to send news: {e}");
-                            counter!("dapnet_event_announcements", "result" => "error")
-                                .increment(1);
-                        }
-                    }
-                }
-            }
+async fn async_main(mut cli: Cli) -> anyhow::Result<()> {
+    // Log output to stdout would be drawn over the monitor, and would swamp the bench results
+    let _log_guard = logging::init(
+        !matches!(cli.command, Some(Command::Monitor | Command::Bench { .. })),
+        cli.log_directory
+            .as_deref()
+            .map(|directory| LogFileSettings {
+                directory,
+                rotation: cli.log_rotation,
+                max_size: cli.log_max_size * 1024 * 1024,
+                max_files: cli.log_max_files,
+            }),
+    )?;
+
+    if let Some(profile) = &cli.profile {
+        info!(
+            "Using profile {profile} from {}",
+            cli.profile_file.display()
+        );
+    }
+
+    if let Some(proxy) = &cli.proxy {
+        info!("Using proxy {proxy}");
+    }
+
+    expand_venue_groups(&mut cli)?;
+
+    match &cli.command {
+        Some(Command::AnnounceEvent { id, kind, send }) => {
+            let schedule = ScheduleSource::new(cli.api_url.clone(), cli.schedule_overrides.clone());
+            let rubric_slots = build_rubric_slots(&cli, &schedule).await?;
+            let dapnet = if *send {
+                Some(build_dapnet(&cli)?)
+            } else {
+                None
+            };
+            let retry_policy = build_retry_policy(&cli);
+            announce_event::run(
+                &schedule,
+                &build_news_settings(&cli, rubric_slots, Arc::new(SystemClock))?,
+                *id,
+                *kind,
+                dapnet.as_ref().map(|dapnet| (dapnet, &retry_policy)),
+            )
+            .await
         }
-        Err(e) => {
-            warn!("{e}");
+        Some(Command::Bench {
+            schedule,
+            iterations,
+        }) => {
+            let (schedule, iterations) = (schedule.clone(), *iterations);
+            let event_start_offset = build_event_start_offset(&cli)?;
+            let clock = Arc::new(MockClock::new(Utc::now()));
+
+            // Announcements go nowhere, so nothing about them is recorded either
+            let mut cli = cli;
+            cli.dry_run = false;
+            cli.dry_run_output = None;
+            cli.state_file = None;
+
+            let news_settings = build_news_settings(&cli, None, clock.clone())?;
+            let plan = Arc::new(build_plan(&cli)?);
+            let health = Arc::new(Health::new(std::time::Duration::from_secs(
+                cli.health_stall_timeout,
+            )));
+            let mut ctx = build_context(
+                cli,
+                clock.clone(),
+                ActivityFeed::new(),
+                plan,
+                Dapnet::new("", ""),
+                news_settings,
+                health,
+            )?;
+            ctx.notifiers = vec![Box::new(bench::NullNotifier)];
+
+            bench::run(&ctx, &schedule, iterations, event_start_offset, &clock).await
+        }
+        Some(Command::CheckVenues) => {
+            let schedule = ScheduleSource::new(cli.api_url.clone(), cli.schedule_overrides.clone());
+            let rubric_slots = build_rubric_slots(&cli, &schedule).await?;
+            venue_check::run(
+                &schedule,
+                &build_news_settings(&cli, rubric_slots, Arc::new(SystemClock))?,
+                cli.strict_venue_table,
+            )
+            .await
+        }
+        Some(Command::Stats) => stats::run(cli.api_url.clone()).await,
+        Some(Command::Plan) => {
+            let plan = build_plan(&cli)?;
+            let rubric_slots = build_rubric_slots(&cli, &plan.schedule).await?;
+            plan.print(&build_news_settings(
+                &cli,
+                rubric_slots,
+                Arc::new(SystemClock),
+            )?)
+            .await
+        }
+        Some(Command::Monitor) | None => {
+            let metrics = PrometheusBuilder::new().install_recorder()?;
+            spawn_metrics_upkeep(metrics.clone());
+            run(cli, metrics).await
         }
-        _ => {}
     }
 }
 
-async fn send_startup_page(dapnet: &DapnetClient) -> anyhow::Result<()> {
-    info!("Checking DAPNET connection...");
+/// Replaces references to venue groups (`@name`) in the options that take venue names with the
+/// venues in each group.
+fn expand_venue_groups(cli: &mut Cli) -> anyhow::Result<()> {
+    let venue_map = cli
+        .venue_map
+        .as_deref()
+        .map(|path| VenueMap::load(path, cli.strict_venue_table))
+        .transpose()?;
+    let expand = |names: &[String]| venue_map::expand_venue_groups(venue_map.as_ref(), names);
 
-    match dapnet
-        .new_call(
-            &OutgoingCallBuilder::default()
-                .text(format!(
-                    "M0NXN: EMF sched. anncr. start at {}",
-                    Utc::now().format("%d %H:%M %Z")
-                ))
-                .recipients(vec!["m0nxn".to_string()])
-                .transmitter_groups(vec!["uk-all".to_string()])
-                .build()?,
-        )
-        .await
-    {
-        Ok(()) => {
-            info!("Could send a page, assuming DAPNET connection is working");
-        }
-        Err(e) => {
-            warn!("Failed to send a page, something's fucky... {e}");
-        }
-    };
+    cli.include_venue = expand(&cli.include_venue)?;
+    cli.exclude_venue = expand(&cli.exclude_venue)?;
+    cli.venue_priority = expand(&cli.venue_priority)?;
+
+    let mut event_tier = Vec::new();
+    for rule in std::mem::take(&mut cli.event_tier) {
+        event_tier.extend(rule.expand_venue_groups(expand)?);
+    }
+    cli.event_tier = event_tier;
 
     Ok(())
 }
+
+fn build_dapnet(cli: &Cli) -> anyhow::Result<Dapnet> {
+    let (username, password) = resolve_dapnet_credentials(cli)?;
+
+    let mut http = reqwest::Client::builder();
+    if let Some(proxy) = &cli.proxy {
+        http = http.proxy(reqwest::Proxy::all(proxy.as_str())?);
+    }
+
+    let http = http
+        .connect_timeout(std::time::Duration::from_secs(cli.dapnet_connect_timeout))
+        .read_timeout(std::time::Duration::from_secs(cli.dapnet_read_timeout))
+        .dns_resolver(Arc::new(CachingResolver::new(
+            std::time::Duration::from_secs(cli.dns_cache_ttl),
+        )))
+        .build()?;
+
+    Ok(Dapnet::new(&username, &password)
+        .with_http_client(http)
+        .with_api_urls(&cli.dapnet_url)
+        .with_password_command(cli.dapnet_password_command.clone()))
+}
+
+/// Gets the DAPNET username and password from wherever they were provided.
+fn resolve_dapnet_credentials(cli: &Cli) -> anyhow::Result<(String, String)> {
+    let username = credentials::resolve(
+        "DAPNET username",
+        CredentialSource {
+            value: cli.dapnet_username.as_deref(),
+            file: cli.dapnet_username_file.as_deref(),
+            command: None,
+        },
+    )?;
+    let password = credentials::resolve(
+        "DAPNET password",
+        CredentialSource {
+            value: cli.dapnet_password.as_deref(),
+            file: cli.dapnet_password_file.as_deref(),
+            command: cli.dapnet_password_command.as_deref(),
+        },
+    )?;
+
+    Ok((username, password))
+}
+
+/// Parses a signed duration such as "-6days", "+2h" or "90min".
+fn parse_time_offset(s: &str) -> anyhow::Result<Duration> {
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(s) => (true, s),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (amount, unit) = s.split_at(split);
+    let amount: i64 = amount
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Time offset must start with a number"))?;
+
+    let offset = match unit.trim() {
+        "s" | "sec" | "secs" | "seconds" => Duration::try_seconds(amount),
+        "m" | "min" | "mins" | "minutes" => Duration::try_minutes(amount),
+        "h" | "hour" | "hours" => Duration::try_hours(amount),
+        "d" | "day" | "days" => Duration::try_days(amount),
+        unit => return Err(anyhow::anyhow!("Unknown time offset unit \"{unit}\"")),
+    }
+    .ok_or_else(|| anyhow::anyhow!("Time offset is too large"))?;
+
+    Ok(if negative { -offset } else { offset })
+}
+
+/// Parses a proxy URL, which must be an HTTP(S) or SOCKS5 proxy.
+fn parse_proxy(s: &str) -> anyhow::Result<Url> {
+    let url = Url::parse(s).map_err(|e| anyhow::anyhow!("Invalid proxy URL: {e}"))?;
+
+    match url.scheme() {
+        "http" | "https" | "socks5" | "socks5h" => Ok(url),
+        scheme => Err(anyhow::anyhow!(
+            "Unsupported proxy scheme \"{scheme}\", only http, https, socks5 and socks5h proxies are supported"
+        )),
+    }
+}
+
+fn parse_track_rubric(s: &str) -> anyhow::Result<(String, String)> {
+    let (event_type, rubric) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow::anyhow!("Track rubric must be in the form type=rubric"))?;
+
+    Ok((event_type.to_lowercase(), rubric.to_string()))
+}
+
+fn parse_event_template(s: &str) -> anyhow::Result<MessageTemplate> {
+    MessageTemplate::new(s, template::EVENT_FIELDS)
+}
+
+fn parse_startup_page_template(s: &str) -> anyhow::Result<MessageTemplate> {
+    MessageTemplate::new(s, template::STARTUP_PAGE_FIELDS)
+}
+
+fn parse_shutdown_page_template(s: &str) -> anyhow::Result<MessageTemplate> {
+    MessageTemplate::new(s, template::SHUTDOWN_PAGE_FIELDS)
+}
+
+fn parse_char_replacement(s: &str) -> anyhow::Result<(String, String)> {
+    let (from, to) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow::anyhow!("Character replacement must be in the form from=to"))?;
+
+    if from.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Character replacement has nothing to replace"
+        ));
+    }
+
+    Ok((from.to_string(), to.to_string()))
+}
+
+fn build_charset_settings(cli: &Cli) -> CharsetSettings {
+    CharsetSettings {
+        policy: cli.charset_policy,
+        transliterate: cli.transliterate,
+        replacements: cli.char_replacement.clone(),
+    }
+}
+
+/// Gets the transmitter groups given for a particular kind of call, falling back to the
+/// transmitter groups used for all calls.
+fn transmitter_groups_or_default(cli: &Cli, transmitter_groups: &[String]) -> Vec<String> {
+    if transmitter_groups.is_empty() {
+        cli.transmitter_group.clone()
+    } else {
+        transmitter_groups.to_vec()
+    }
+}
+
+fn build_retry_policy(cli: &Cli) -> RetryPolicy {
+    RetryPolicy {
+        request_timeout: std::time::Duration::from_secs(cli.dapnet_timeout),
+        max_attempts: cli.dapnet_retry_attempts,
+        initial_delay: std::time::Duration::from_millis(cli.dapnet_retry_initial_delay),
+        max_duration: std::time::Duration::from_secs(cli.dapnet_retry_max_duration),
+    }
+}
+
+fn build_news_settings(
+    cli: &Cli,
+    slots: Option<SlotAssignment>,
+    clock: Arc<dyn Clock>,
+) -> anyhow::Result<NewsSettings> {
+    Ok(NewsSettings {
+        rubric: cli
+            .shadow_rubric
+            .clone()
+            .unwrap_or_else(|| "emfcamp".to_string()),
+        track_rubrics: if cli.shadow_rubric.is_some() {
+            HashMap::new()
+        } else {
+            cli.track_rubric.iter().cloned().collect()
+        },
+        venue_rubrics: cli.shadow_rubric.is_none(),
+        slots,
+        venue_map: cli
+            .venue_map
+            .as_deref()
+            .map(|path| VenueMap::load(path, cli.strict_venue_table))
+            .transpose()?,
+        max_length: cli.max_news_length,
+        description_snippet: cli.description_snippet,
+        relative_start_time: cli.relative_start_time,
+        upcoming_template: cli.message_template.clone(),
+        starting_now_template: cli.starting_now_message_template.clone(),
+        call_template: cli.call_message_template.clone(),
+        time_offset: cli.time_offset,
+        clock,
+        charset: build_charset_settings(cli),
+        redacted_fields: cli.redact_field.clone(),
+    })
+}
+
+fn build_event_start_offset(cli: &Cli) -> anyhow::Result<Duration> {
+    Ok(-Duration::try_seconds(cli.pre_event_announcement_time)
+        .ok_or_else(|| anyhow::anyhow!("Invalid pre event announcement time"))?)
+}
+
+/// Builds the filters for upcoming and starting now announcements.
+fn build_event_filters(cli: &Cli) -> anyhow::Result<(EventFilter, EventFilter)> {
+    let event_filter = EventFilter {
+        min_duration: parse_minutes(cli.min_duration, "minimum duration")?,
+        max_duration: parse_minutes(cli.max_duration, "maximum duration")?,
+        enabled_weekdays: cli.enabled_weekdays.clone(),
+        enabled_dates: cli.enabled_dates.clone(),
+        include_venues: cli.include_venue.clone(),
+        exclude_venues: cli.exclude_venue.clone(),
+        include_types: cli.include_type.clone(),
+        exclude_title: cli.exclude_title_regex.clone(),
+    };
+
+    let starting_now_filter = EventFilter {
+        min_duration: parse_minutes(cli.starting_now_min_duration, "minimum duration")?,
+        max_duration: parse_minutes(cli.starting_now_max_duration, "maximum duration")?,
+        enabled_weekdays: cli.enabled_weekdays.clone(),
+        enabled_dates: cli.enabled_dates.clone(),
+        include_venues: cli.include_venue.clone(),
+        exclude_venues: cli.exclude_venue.clone(),
+        include_types: cli.include_type.clone(),
+        exclude_title: cli.exclude_title_regex.clone(),
+    };
+
+    Ok((event_filter, starting_now_filter))
+}
+
+fn build_plan(cli: &Cli) -> anyhow::Result<AnnouncementPlan> {
+    let (filter, starting_now_filter) = build_event_filters(cli)?;
+
+    Ok(AnnouncementPlan {
+        schedule: ScheduleSource::new(cli.api_url.clone(), cli.schedule_overrides.clone()),
+        event_start_offset: build_event_start_offset(cli)?,
+        time_offset: cli.time_offset,
+        filter,
+        starting_now_filter: cli
+            .starting_now_announcements
+            .then_some(starting_now_filter),
+        tiers: TierSettings {
+            rules: cli.event_tier.clone(),
+            headline: cli.headline_delivery,
+            normal: cli.normal_delivery,
+            low: cli.low_delivery,
+        },
+        suppressed_events_file: cli.suppressed_events_file.clone(),
+    })
+}
+
+async fn build_rubric_slots(
+    cli: &Cli,
+    schedule: &ScheduleSource,
+) -> anyhow::Result<Option<SlotAssignment>> {
+    if cli.auto_rubric_slots {
+        let events = schedule.events().await?;
+        Ok(Some(SlotAssignment::new(
+            events.into_iter().map(|e| e.venue),
+            &cli.venue_priority,
+            cli.slot_overflow_policy,
+        )))
+    } else {
+        Ok(None)
+    }
+}
+
+fn build_call_settings(cli: &Cli) -> CallSettings {
+    CallSettings {
+        max_recipients_per_call: cli.max_recipients_per_call,
+        charset: build_charset_settings(cli),
+        max_length: cli.max_call_length,
+        overlong_policy: cli.overlong_calls,
+        recipient_allowlist: cli.recipient_allowlist.clone(),
+        recipient_blocklist: cli.recipient_blocklist.clone(),
+        transmitter_groups: cli.transmitter_group.clone(),
+        retry: build_retry_policy(cli),
+    }
+}
+
+fn build_startup_page_settings(cli: &Cli) -> StartupPageSettings {
+    StartupPageSettings {
+        template: cli.startup_page_template.clone(),
+        recipients: if cli.startup_page_recipients.is_empty() {
+            vec![cli.operator_callsign.clone()]
+        } else {
+            cli.startup_page_recipients.clone()
+        },
+        transmitter_groups: transmitter_groups_or_default(
+            cli,
+            &cli.startup_page_transmitter_groups,
+        ),
+        timeout: std::time::Duration::from_secs(cli.startup_page_timeout),
+        required: cli.require_dapnet_check,
+        shutdown_template: cli
+            .shutdown_page
+            .then(|| cli.shutdown_page_template.clone()),
+    }
+}
+
+/// Builds every target announcements are delivered to.
+fn build_notifiers(cli: &Cli, news_settings: &NewsSettings) -> Vec<Box<dyn Notifier>> {
+    let tier_call_transmitter_groups =
+        transmitter_groups_or_default(cli, &cli.tier_call_transmitter_groups);
+
+    // Subscribers must not be paged in shadow mode, so calls go to the shadow recipients instead
+    // (or are not made if there are none)
+    let call_recipients = |recipients: &[String]| match &cli.shadow_rubric {
+        Some(_) if !recipients.is_empty() => cli.shadow_call_recipients.clone(),
+        _ => recipients.to_vec(),
+    };
+
+    let mut call_notifiers = Vec::new();
+    call_notifiers.push(CallNotifier {
+        name: "tier".to_string(),
+        recipients: call_recipients(&cli.tier_call_recipients),
+        transmitter_groups: tier_call_transmitter_groups.clone(),
+        tiered: true,
+        venues: Vec::new(),
+    });
+    for (name, recipients) in &cli.call_target {
+        call_notifiers.push(CallNotifier {
+            name: name.clone(),
+            recipients: call_recipients(recipients),
+            transmitter_groups: tier_call_transmitter_groups.clone(),
+            tiered: false,
+            venues: Vec::new(),
+        });
+    }
+    for venue in news_settings.venue_map.iter().flat_map(|map| map.venues()) {
+        call_notifiers.push(CallNotifier {
+            name: format!("venue:{}", venue.name),
+            recipients: call_recipients(&venue.call_recipients),
+            transmitter_groups: tier_call_transmitter_groups.clone(),
+            tiered: false,
+            venues: vec![venue.name.clone()],
+        });
+    }
+    for group in news_settings.venue_map.iter().flat_map(|map| map.groups()) {
+        call_notifiers.push(CallNotifier {
+            name: format!("group:{}", group.name),
+            recipients: call_recipients(&group.call_recipients),
+            transmitter_groups: tier_call_transmitter_groups.clone(),
+            tiered: false,
+            venues: group.venues.clone(),
+        });
+    }
+
+    std::iter::once(Box::new(RubricNotifier) as Box<dyn Notifier>)
+        .chain(
+            call_notifiers
+                .into_iter()
+                .filter(|notifier| !notifier.recipients.is_empty())
+                .map(|notifier| Box::new(notifier) as Box<dyn Notifier>),
+        )
+        .collect()
+}
+
+/// Builds everything needed to act on results from the announcer.
+fn build_context(
+    cli: Cli,
+    clock: Arc<dyn Clock>,
+    activity: ActivityFeed,
+    plan: Arc<AnnouncementPlan>,
+    dapnet: Dapnet,
+    news_settings: NewsSettings,
+    health: Arc<Health>,
+) -> anyhow::Result<Context> {
+    let dapnet_health = Mutex::new(SinkHealth::new(
+        "dapnet",
+        cli.dapnet_circuit_breaker_threshold,
+        Duration::try_seconds(cli.dapnet_circuit_breaker_cooldown)
+            .ok_or_else(|| anyhow::anyhow!("Invalid DAPNET circuit breaker cooldown"))?,
+    ));
+
+    Ok(Context {
+        call_settings: build_call_settings(&cli),
+        notifiers: build_notifiers(&cli, &news_settings),
+        startup_page_settings: build_startup_page_settings(&cli),
+        cli,
+        clock,
+        activity,
+        plan,
+        dapnet,
+        dapnet_health,
+        paused: Mutex::new(None),
+        undelivered: Mutex::new(Vec::new()),
+        news_settings,
+        health,
+        http_client: reqwest::Client::new(),
+    })
+}
+
+fn spawn_metrics_upkeep(metrics: PrometheusHandle) {
+    tokio::spawn(async move {
+        let mut upkeep_interval = tokio::time::interval(std::time::Duration::from_secs(5));
+        loop {
+            upkeep_interval.tick().await;
+            metrics.run_upkeep();
+        }
+    });
+}
+
+async fn run(cli: Cli, metrics: PrometheusHandle) -> anyhow::Result<()> {
+    event_news::check_venue_table(cli.strict_venue_table)?;
+
+    if let Some(path) = &cli.schedule_overrides {
+        ScheduleOverrides::load(path)?;
+    }
+
+    if let Some(path) = &cli.venue_map {
+        VenueMap::load(path, cli.strict_venue_table)?;
+    }
+
+    if let Some(path) = &cli.suppressed_events_file {
+        suppress::load_suppressed_events(path)?;
+    }
+
+    let event_start_offset = build_event_start_offset(&cli)?;
+    info!("Event start offset: {:?}", event_start_offset);
+
+    let clock: Arc<dyn Clock> = Arc::new(SystemClock);
+
+    if !cli.time_offset.is_zero() {
+        warn!("Clock is offset by {} for the schedule", cli.time_offset);
+    }
+
+    let observability_authentication = match (
+        &cli.observability_bearer_token,
+        &cli.observability_basic_auth,
+    ) {
+        (Some(token), _) => Some(Authentication::Bearer(token.clone())),
+        (None, Some(credentials)) => {
+            let (username, password) = credentials.split_once(':').ok_or_else(|| {
+                anyhow::anyhow!("Observability basic auth must be in the form username:password")
+            })?;
+            Some(Authentication::Basic {
+                username: username.to_string(),
+                password: password.to_string(),
+            })
+        }
+        (None, None) => None,
+    };
+    let observability_tls = match (&cli.observability_tls_cert, &cli.observability_tls_key) {
+        (Some(cert), Some(key)) => Some(observability::tls_acceptor(cert, key)?),
+        _ => None,
+    };
+    let activity = ActivityFeed::new();
+    let plan = Arc::new(build_plan(&cli)?);
+    let health = Arc::new(Health::new(std::time::Duration::from_secs(
+        cli.health_stall_timeout,
+    )));
+    let observability = Arc::new(Observability::new(
+        metrics,
+        activity.clone(),
+        plan.clone(),
+        clock.clone(),
+        health.clone(),
+        observability_authentication,
+        observability_tls,
+    ));
+    match &cli.observability_socket {
+        Some(path) => observability.clone().serve_unix(path).await?,
+        None => {
+            for address in &cli.observability_address {
+                observability
+                    .clone()
+                    .serve_tcp(*address, Endpoints::All)
+                    .await?;
+            }
+        }
+    }
+    for address in &cli.metrics_address {
+        observability
+            .clone()
+            .serve_tcp(*address, Endpoints::Metrics)
+            .await?;
+    }
+    for address in &cli.status_address {
+        observability
+            .clone()
+            .serve_tcp(*address, Endpoints::Status)
+            .await?;
+    }
+
+    build_info::register_metric();
+    info!("Version {}", build_info::LONG_VERSION);
+
+    describe_counter!(
+        "dapnet_event_announcements",
+        "Number of announcements sent to DAPNET"
+    );
+    describe_counter!(
+        "dns_stale_lookups",
+        "Number of times addresses from an earlier lookup were used as a lookup failed"
+    );
+    describe_counter!(
+        "dapnet_core_requests",
+        "Number of news and calls sent to each DAPNET core"
+    );
+    describe_counter!(
+        "dapnet_news_verifications",
+        "Number of checks that sent news is present in the rubric"
+    );
+    describe_gauge!(
+        "sending_paused",
+        "Whether sending news and calls is paused (toggled by SIGUSR2)"
+    );
+    describe_gauge!(
+        "dapnet_undelivered_news",
+        "News waiting for DAPNET to be reachable again"
+    );
+    describe_counter!(
+        "notifier_announcements",
+        "Number of announcements sent to each notification target"
+    );
+    describe_counter!(
+        "dapnet_calls",
+        "Number of calls sent to DAPNET, by whether all, some or none of the recipients were paged"
+    );
+    describe_counter!(
+        "dapnet_call_chunks",
+        "Number of call chunks sent to DAPNET, not including retries"
+    );
+    describe_counter!(
+        "dapnet_call_recipients",
+        "Number of recipients calls were sent to in full, in part (some parts of a call that was split) and not at all"
+    );
+    describe_counter!(
+        "dapnet_blocked_recipients",
+        "Number of recipients removed from calls by the allowlist/blocklist"
+    );
+    describe_counter!(
+        "dapnet_duplicate_announcements_suppressed",
+        "Number of announcements not sent as they duplicate a recent one"
+    );
+    describe_counter!(
+        "news_unsafe_characters",
+        "Number of messages containing characters that cannot be sent via POCSAG"
+    );
+    describe_counter!(
+        "now_next_announcements",
+        "Number of now and next news items sent"
+    );
+    describe_counter!(
+        "daily_digests",
+        "Number of daily digests of headline events sent"
+    );
+    describe_counter!(
+        "schedule_change_announcements",
+        "Number of cancelled and moved events announced"
+    );
+    describe_counter!(
+        "pocsag_transliterated_messages",
+        "Number of messages that had characters replaced with an ASCII equivalent"
+    );
+    describe_counter!(
+        "dapnet_news_overwrites_prevented",
+        "Number of news items not sent to avoid overwriting news for an event that starts sooner"
+    );
+    describe_gauge!(
+        "announcement_watchdog_triggered",
+        "Set when no announcements have been sent for longer than the watchdog time"
+    );
+    describe_gauge!(
+        "next_announcement_overdue",
+        "Set when the next announcement was due more than the grace period ago and has not been attempted"
+    );
+    describe_gauge!(
+        "next_announcement_timestamp_seconds",
+        "Time the next announcement is due"
+    );
+    describe_gauge!(
+        "clock_skew_seconds",
+        "Difference between the local clock and the schedule API clock"
+    );
+    describe_counter!(
+        "dapnet_retries",
+        "Number of times news or a call was retried after failing"
+    );
+    describe_counter!(
+        "dapnet_retries_exhausted",
+        "Number of times news or a call was given up on after every retry failed"
+    );
+    describe_gauge!(
+        "sink_consecutive_failures",
+        "Number of consecutive failed sends to a sink"
+    );
+    describe_gauge!(
+        "sink_circuit_open",
+        "Set when sending to a sink is paused after repeated failures"
+    );
+    describe_gauge!(
+        "sink_last_success_timestamp_seconds",
+        "Time of the last successful send to a sink"
+    );
+
+    // Setup schedule API client
+    let schedule_client = ScheduleClient::new(cli.api_url.clone());
+
+    let mut announcer = Announcer::new(
+        AnnouncerSettingsBuilder::default()
+            .event_start_offset(event_start_offset - cli.time_offset)
+            .build()?,
+        schedule_client,
+    )
+    .await?;
+
+    let mut starting_now_announcer = if cli.starting_now_announcements {
+        Some(
+            Announcer::new(
+                AnnouncerSettingsBuilder::default()
+                    .event_start_offset(-cli.time_offset)
+                    .build()?,
+                ScheduleClient::new(cli.api_url.clone()),
+            )
+            .await?,
+        )
+    } else {
+        None
+    };
+
+    // Setup and test DAPNET client
+    let dapnet = build_dapnet(&cli)?;
+    let call_settings = build_call_settings(&cli);
+
+    let startup_page_settings = build_startup_page_settings(&cli);
+    let mode = if cli.dry_run {
+        "dry run"
+    } else if cli.shadow_rubric.is_some() {
+        "shadow"
+    } else {
+        "live"
+    };
+    if !cli.dry_run {
+        let transmitter_groups = call_settings
+            .transmitter_groups
+            .iter()
+            .chain(&startup_page_settings.transmitter_groups)
+            .chain(&cli.tier_call_transmitter_groups)
+            .map(String::as_str)
+            .collect();
+        startup::check_transmitter_groups(&dapnet, &transmitter_groups).await?;
+    }
+
+    let send_startup_page = cli.startup_page.should_send(cli.dry_run);
+    if cli.require_dapnet_check && (!send_startup_page || cli.startup_page_background) {
+        return Err(anyhow::anyhow!(
+            "The DAPNET check requires the startup page to be sent, and not in the background"
+        ));
+    }
+
+    if !send_startup_page {
+        info!("Not sending startup page");
+    } else if cli.startup_page_background {
+        // Uses its own clients so that it can run alongside the main loop
+        let dapnet = build_dapnet(&cli)?;
+        let call_settings = call_settings.clone();
+        let startup_page_settings = startup_page_settings.clone();
+        let schedule = ScheduleSource::new(cli.api_url.clone(), cli.schedule_overrides.clone());
+        let now = clock.now();
+        let time_offset = cli.time_offset;
+        tokio::spawn(async move {
+            startup::send_startup_page(
+                &dapnet,
+                &call_settings,
+                &startup_page_settings,
+                mode,
+                &schedule,
+                now,
+                time_offset,
+            )
+            .await
+        });
+    } else {
+        startup::send_startup_page(
+            &dapnet,
+            &call_settings,
+            &startup_page_settings,
+            mode,
+            &plan.schedule,
+            clock.now(),
+            cli.time_offset,
+        )
+        .await?;
+    }
+
+    let rubric_slots = build_rubric_slots(&cli, &plan.schedule).await?;
+    let news_settings = build_news_settings(&cli, rubric_slots, clock.clone())?;
+    if cli.shadow_rubric.is_some() {
+        warn!(
+            "Shadow mode, sending news to rubric {}",
+            news_settings.rubric
+        );
+    }
+
+    if !cli.dry_run {
+        for rubric in news_settings.rubrics() {
+            startup::check_rubric_permission(&dapnet, dapnet.username(), rubric).await?;
+        }
+    }
+    // Nothing is sent to DAPNET in a dry run, so there is nothing for authentication to block
+    health.record_dapnet_authenticated();
+
+    // The summary is a real page like the startup page, so is only sent when that is and never in
+    // a dry run
+    if cli.startup_summary_page && send_startup_page && !cli.dry_run {
+        startup::send_startup_summary_page(
+            &dapnet,
+            &call_settings,
+            &startup_page_settings,
+            &plan.schedule,
+            clock.now() + cli.time_offset,
+        )
+        .await;
+    }
+
+    let mut monitor_commands = if matches!(cli.command, Some(Command::Monitor)) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        monitor::spawn(plan.clone(), clock.clone(), &activity, sender);
+        Some(receiver)
+    } else {
+        None
+    };
+
+    let ctx = build_context(cli, clock, activity, plan, dapnet, news_settings, health)?;
+
+    let mut state = AnnouncementState::new(&ctx)?;
+
+    let announcement_overdue_grace = Duration::try_seconds(ctx.cli.announcement_overdue_grace)
+        .ok_or_else(|| anyhow::anyhow!("Invalid announcement overdue grace"))?;
+    let mut adhoc_interval =
+        tokio::time::interval(std::time::Duration::from_secs(ctx.cli.adhoc_poll_interval));
+
+    let mut moved_events_interval = tokio::time::interval(std::time::Duration::from_secs(30));
+    let mut overdue_check_interval = tokio::time::interval(std::time::Duration::from_secs(60));
+    let mut slot_rotation_interval = tokio::time::interval(std::time::Duration::from_secs(
+        ctx.cli.slot_rotation_interval.unwrap_or(60),
+    ));
+
+    let mut outage_tracker = match ctx.cli.schedule_outage_alert_time {
+        Some(t) => Some(OutageTracker::new(
+            Duration::try_seconds(t)
+                .ok_or_else(|| anyhow::anyhow!("Invalid schedule outage alert time"))?,
+        )),
+        None => None,
+    };
+
+    let mut announcement_watchdog = match ctx.cli.announcement_watchdog_time {
+        Some(t) => Some(AnnouncementWatchdog::new(
+            ctx.cli.event_days.clone(),
+            Duration::try_hours(t)
+                .ok_or_else(|| anyhow::anyhow!("Invalid announcement watchdog time"))?,
+            ctx.schedule_now(),
+        )),
+        None => None,
+    };
+    let mut watchdog_interval = tokio::time::interval(std::time::Duration::from_secs(300));
+
+    let mut schedule_check_interval = tokio::time::interval(std::time::Duration::from_secs(
+        ctx.cli.schedule_check_interval,
+    ));
+
+    let heartbeat_period = std::time::Duration::from_secs(ctx.cli.heartbeat_interval * 60 * 60);
+    let mut heartbeat_interval = tokio::time::interval_at(
+        tokio::time::Instant::now() + heartbeat_period,
+        heartbeat_period,
+    );
+
+    let clock_skew_threshold = Duration::try_seconds(ctx.cli.clock_skew_threshold)
+        .ok_or_else(|| anyhow::anyhow!("Invalid clock skew threshold"))?;
+    let mut clock_check_interval =
+        tokio::time::interval(std::time::Duration::from_secs(ctx.cli.clock_check_interval));
+
+    let mut now_next_interval = tokio::time::interval(std::time::Duration::from_secs(
+        ctx.cli.now_next_interval.max(1) * 60,
+    ));
+
+    let mut digest_scheduler = ctx.cli.digest_time.map(DigestScheduler::new);
+    let mut digest_interval = tokio::time::interval(std::time::Duration::from_secs(60));
+
+    let mut terminate_signal = signal(SignalKind::terminate())?;
+    let mut pause_signal = signal(SignalKind::user_defined2())?;
+    gauge!("sending_paused").set(0.0);
+    gauge!("dapnet_undelivered_news").set(0.0);
+    let mut undelivered_interval = tokio::time::interval(std::time::Duration::from_secs(30));
+
+    loop {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {
+                shutdown(&ctx, &state, "ctrl-c").await;
+                return Ok(());
+            }
+            _ = terminate_signal.recv() => {
+                shutdown(&ctx, &state, "SIGTERM").await;
+                return Ok(());
+            }
+            _ = pause_signal.recv() => {
+                toggle_pause(&ctx).await;
+            }
+            Some(command) = recv_monitor_command(&mut monitor_commands) => {
+                match command {
+                    MonitorCommand::SendNews { number, text } => {
+                        info!("News from monitor for slot {number}: {text}");
+                        send_operator_news(&ctx, number, text).await;
+                    }
+                    MonitorCommand::Quit => {
+                        shutdown(&ctx, &state, "quit").await;
+                        return Ok(());
+                    }
+                }
+            }
+            msg = announcer.poll() => {
+                ctx.health.record_activity();
+                ctx.health.record_schedule_fetch(msg.is_ok());
+                if let Some(tracker) = outage_tracker.as_mut() {
+                    track_schedule_outage(&ctx, tracker, &msg).await;
+                }
+                let poll_ok = msg.is_ok();
+                if handle_announcer_event(&ctx, &mut state, AnnouncementKind::Upcoming, msg).await {
+                    if let Some(watchdog) = announcement_watchdog.as_mut() {
+                        watchdog.record_sent(ctx.schedule_now());
+                        gauge!("announcement_watchdog_triggered").set(0.0);
+                    }
+                }
+                if poll_ok {
+                    ping_healthcheck(&ctx);
+                }
+            }
+            msg = poll_optional_announcer(&mut starting_now_announcer) => {
+                ctx.health.record_activity();
+                handle_announcer_event(&ctx, &mut state, AnnouncementKind::StartingNow, msg).await;
+            }
+            _ = moved_events_interval.tick(), if ctx.cli.schedule_overrides.is_some() => {
+                if announce_moved_events(&ctx, &mut state).await {
+                    if let Some(watchdog) = announcement_watchdog.as_mut() {
+                        watchdog.record_sent(ctx.schedule_now());
+                        gauge!("announcement_watchdog_triggered").set(0.0);
+                    }
+                }
+            }
+            _ = schedule_check_interval.tick(), if ctx.cli.announce_speaker_changes || ctx.cli.page_schedule_changes || ctx.cli.announce_schedule_changes => {
+                check_schedule(&ctx, &mut state).await;
+            }
+            _ = watchdog_interval.tick(), if announcement_watchdog.is_some() => {
+                if let Some(watchdog) = announcement_watchdog.as_mut() {
+                    check_announcement_watchdog(
+                        &ctx,
+                        watchdog,
+                        event_start_offset,
+                    )
+                    .await;
+                }
+            }
+            _ = slot_rotation_interval.tick(), if state.slot_rotation.is_some() => {
+                if let Some(slot_rotation) = state.slot_rotation.as_mut() {
+                    for news in slot_rotation.rotate(ctx.schedule_now()) {
+                        info!("Rotating shared rubric slot to: {:?}", news);
+                        send_news(&ctx, &news).await;
+                    }
+                }
+            }
+            _ = heartbeat_interval.tick(), if ctx.cli.heartbeat_callsign.is_some() => {
+                if let Some(callsign) = &ctx.cli.heartbeat_callsign {
+                    send_heartbeat_page(&ctx, callsign).await;
+                }
+            }
+            _ = now_next_interval.tick(), if ctx.cli.now_next_rubric.is_some() => {
+                if let Some(rubric) = &ctx.cli.now_next_rubric {
+                    send_now_next(&ctx, &mut state, rubric).await;
+                }
+            }
+            _ = digest_interval.tick(), if digest_scheduler.is_some() => {
+                if let Some(digest_scheduler) = digest_scheduler.as_mut() {
+                    send_digest(&ctx, digest_scheduler).await;
+                }
+            }
+            _ = adhoc_interval.tick(), if ctx.cli.adhoc_announcements.is_some() => {
+                if let Some(path) = &ctx.cli.adhoc_announcements {
+                    send_adhoc_announcements(&ctx, path).await;
+                }
+            }
+            _ = undelivered_interval.tick() => {
+                send_undelivered_news(&ctx).await;
+            }
+            _ = overdue_check_interval.tick() => {
+                check_next_announcement_overdue(
+                    &ctx,
+                    state.last_announcement_attempt,
+                    announcement_overdue_grace,
+                )
+                .await;
+            }
+            _ = clock_check_interval.tick() => {
+                clock_check::check_clock(&ctx.http_client, &ctx.cli.api_url, clock_skew_threshold).await;
+            }
+        }
+    }
+}
+
+fn parse_minutes(minutes: Option<i64>, name: &str) -> anyhow::Result<Option<Duration>> {
+    minutes
+        .map(|m| Duration::try_minutes(m).ok_or_else(|| anyhow::anyhow!("Invalid {name}")))
+        .transpose()
+}
+
+async fn recv_monitor_command(
+    commands: &mut Option<UnboundedReceiver<MonitorCommand>>,
+) -> Option<MonitorCommand> {
+    match commands {
+        Some(commands) => commands.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+async fn poll_optional_announcer(
+    announcer: &mut Option<Announcer>,
+) -> emfcamp_schedule_api::Result<AnnouncerPollResult> {
+    match announcer {
+        Some(announcer) => announcer.poll().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Everything needed to act on results from the announcer.
+struct Context {
+    cli: Cli,
+    clock: Arc<dyn Clock>,
+    activity: ActivityFeed,
+    plan: Arc<AnnouncementPlan>,
+    dapnet: Dapnet,
+    dapnet_health: Mutex<SinkHealth>,
+
+    /// News and calls waiting to be sent while sending is paused, `None` when not paused
+    paused: Mutex<Option<Vec<PausedSend>>>,
+
+    /// News that was not sent as DAPNET was unreachable or its circuit breaker was open, sent
+    /// once it can be
+    undelivered: Mutex<Vec<RubricNews>>,
+
+    call_settings: CallSettings,
+    news_settings: NewsSettings,
+
+    /// Everywhere announcements are delivered to
+    notifiers: Vec<Box<dyn Notifier>>,
+
+    /// Also used for the shutdown page
+    startup_page_settings: StartupPageSettings,
+
+    health: Arc<Health>,
+
+    /// For requests other than to the schedule API and DAPNET (e.g. healthcheck pings)
+    http_client: reqwest::Client,
+}
+
+impl Context {
+    /// The current time as seen by the schedule, i.e. with the time offset applied.
+    fn schedule_now(&self) -> DateTime<Utc> {
+        self.clock.now() + self.cli.time_offset
+    }
+}
+
+/// State that changes as announcements are made.
+struct AnnouncementState {
+    announced_events: AnnouncedEvents,
+    slot_occupancy: SlotOccupancy,
+    duplicate_suppressor: Option<DuplicateSuppressor>,
+    slot_rotation: Option<SlotRotation>,
+
+    /// When the most recent announcement that was attempted was due
+    last_announcement_attempt: DateTime<Utc>,
+
+    schedule_changes: ScheduleChangeTracker,
+
+    /// When events with a corrected start were last checked for announcements that are due
+    moved_events_checked: DateTime<Utc>,
+}
+
+impl AnnouncementState {
+    /// Creates the state for a fresh start, loading the events already announced from the state
+    /// file if there is one.
+    fn new(ctx: &Context) -> anyhow::Result<Self> {
+        Ok(Self {
+            announced_events: match &ctx.cli.state_file {
+                Some(path) => {
+                    let announced_events = AnnouncedEvents::load(path)?;
+                    info!(
+                        "Loaded {} announced events from {}",
+                        announced_events.len(),
+                        path.display()
+                    );
+                    announced_events
+                }
+                None => AnnouncedEvents::default(),
+            },
+            slot_occupancy: SlotOccupancy::default(),
+            duplicate_suppressor: match ctx.cli.duplicate_suppression_window {
+                Some(t) => Some(DuplicateSuppressor::new(
+                    Duration::try_seconds(t)
+                        .ok_or_else(|| anyhow::anyhow!("Invalid duplicate suppression window"))?,
+                )),
+                None => None,
+            },
+            slot_rotation: ctx
+                .cli
+                .slot_rotation_interval
+                .map(|_| SlotRotation::default()),
+            last_announcement_attempt: ctx.clock.now(),
+            schedule_changes: ScheduleChangeTracker::default(),
+            moved_events_checked: ctx.clock.now(),
+        })
+    }
+}
+
+/// Handles a result from the announcer, returning true if an announcement was sent.
+async fn handle_announcer_event(
+    ctx: &Context,
+    state: &mut AnnouncementState,
+    kind: AnnouncementKind,
+    msg: emfcamp_schedule_api::Result<AnnouncerPollResult>,
+) -> bool {
+    match msg {
+        Ok(AnnouncerPollResult::Event(mut event)) => {
+            let overrides = ctx.plan.schedule.overrides();
+            if !overrides.apply(&mut event) {
+                info!("Event {} is hidden by the schedule overrides", event.id);
+                return false;
+            }
+
+            // The announcer works from the published start, so an event with a corrected start
+            // is announced by announce_moved_events instead
+            if overrides.moves(event.id) {
+                info!(
+                    "Event {} has a corrected start, announcing it at that time instead",
+                    event.id
+                );
+                return false;
+            }
+
+            announce(ctx, state, kind, &event).await
+        }
+        Err(e) => {
+            warn!("{e}");
+            false
+        }
+        _ => false,
+    }
+}
+
+/// Announces events whose start is corrected by the schedule overrides once their announcements
+/// are due, as the announcer only knows the published start. Returns true if an upcoming
+/// announcement was sent.
+async fn announce_moved_events(ctx: &Context, state: &mut AnnouncementState) -> bool {
+    let now = ctx.clock.now();
+
+    let overrides = ctx.plan.schedule.overrides();
+    if !overrides.moves_any() {
+        state.moved_events_checked = now;
+        return false;
+    }
+
+    let events = match ctx.plan.schedule.events().await {
+        Ok(events) => events,
+        Err(e) => {
+            warn!("Failed to fetch schedule for events with a corrected start: {e}");
+            return false;
+        }
+    };
+
+    let since = std::mem::replace(&mut state.moved_events_checked, now);
+
+    let mut kinds = vec![AnnouncementKind::Upcoming];
+    if ctx.cli.starting_now_announcements {
+        kinds.push(AnnouncementKind::StartingNow);
+    }
+
+    let mut sent = false;
+
+    for event in events.iter().filter(|event| overrides.moves(event.id)) {
+        for &kind in &kinds {
+            let time = ctx.plan.announcement_time(event, kind);
+            if time > since
+                && time <= now
+                && announce(ctx, state, kind, event).await
+                && matches!(kind, AnnouncementKind::Upcoming)
+            {
+                sent = true;
+            }
+        }
+    }
+
+    sent
+}
+
+/// Announces an event from the (corrected) schedule, returning true if an announcement was sent.
+async fn announce(
+    ctx: &Context,
+    state: &mut AnnouncementState,
+    kind: AnnouncementKind,
+    event: &Event,
+) -> bool {
+    if let Some(reason) = ctx.plan.rejection_reason(event, kind) {
+        info!("Event {} \"{}\" {reason}", event.id, event.title);
+        return false;
+    }
+
+    let delivery = ctx.plan.tiers.delivery_for(event);
+
+    state.last_announcement_attempt = ctx.plan.announcement_time(event, kind);
+
+    ctx.activity.publish(Activity::Scheduled {
+        event_id: event.id,
+        title: event.title.clone(),
+    });
+
+    state.announced_events.prune(ctx.schedule_now());
+
+    match state.announced_events.check(event, kind) {
+        AnnouncedState::New => {}
+        AnnouncedState::AlreadyAnnounced => {
+            info!("Event {} has already been announced", event.id);
+            return false;
+        }
+        AnnouncedState::Moved { previous_start } => {
+            info!(
+                "Event {} has moved from {previous_start} to {}, announcing again",
+                event.id, event.start
+            );
+        }
+    }
+
+    if let Some(news) = event.to_rubric_news(&ctx.news_settings, kind) {
+        info!("News for event: {:?}", news);
+        ctx.activity.publish(Activity::Formatted {
+            event_id: event.id,
+            news: news.clone(),
+        });
+
+        let event_start = event.start.with_timezone(&Utc);
+
+        let announcement = Announcement {
+            call_text: event
+                .to_call_text(&ctx.news_settings)
+                .unwrap_or_else(|| news.text.clone()),
+            news,
+        };
+
+        let notifiers: Vec<&dyn Notifier> = ctx
+            .notifiers
+            .iter()
+            .map(AsRef::as_ref)
+            .filter(|notifier| notifier.accepts(event, delivery))
+            .filter(|notifier| notifier.prepare(ctx, state, kind, &announcement, event_start))
+            .collect();
+
+        if notifiers.is_empty() {
+            return false;
+        }
+
+        let outcome = notifier::notify_all(ctx, &notifiers, &announcement).await;
+
+        // Only events that went out are recorded, so that an announcement that failed or is still
+        // queued (the queues are not kept over a restart) is made again if the event is given again
+        let announced = outcome.is_announced();
+        if announced {
+            state
+                .announced_events
+                .record(event, kind, ctx.schedule_now());
+            save_announced_events(ctx, state);
+        }
+
+        announced
+    } else {
+        false
+    }
+}
+
+/// Checks that news may be written to its rubric slot and is not a duplicate, recording it as
+/// the slot's content if so.
+fn prepare_news(
+    ctx: &Context,
+    state: &mut AnnouncementState,
+    kind: AnnouncementKind,
+    news: &RubricNews,
+    event_start: DateTime<Utc>,
+) -> bool {
+    if !may_overwrite_slot(ctx, state, news, event_start) {
+        return false;
+    }
+
+    let target = format!("{}/{:?}", news.rubric, news.number);
+    if is_duplicate(ctx, state, &target, &news.text) {
+        return false;
+    }
+
+    state.slot_occupancy.record(news, event_start);
+
+    if let (AnnouncementKind::Upcoming, Some(slot_rotation)) = (kind, state.slot_rotation.as_mut())
+    {
+        slot_rotation.add(event_start, news.clone());
+    }
+
+    true
+}
+
+/// Checks if identical text was sent to the same target within the duplicate suppression window,
+/// recording it as sent now if not.
+fn is_duplicate(ctx: &Context, state: &mut AnnouncementState, target: &str, text: &str) -> bool {
+    let Some(suppressor) = state.duplicate_suppressor.as_mut() else {
+        return false;
+    };
+
+    if suppressor.check_and_record(target, text, ctx.clock.now()) {
+        info!("Identical message was sent to {target} recently, not sending again");
+        counter!("dapnet_duplicate_announcements_suppressed").increment(1);
+        true
+    } else {
+        false
+    }
+}
+
+async fn send_adhoc_announcements(ctx: &Context, path: &Path) {
+    for announcement in adhoc::take_due(path, ctx.clock.now()) {
+        info!("Ad-hoc announcement: {:?}", announcement);
+
+        match announcement.target {
+            AdhocTarget::News { number } => {
+                send_operator_news(ctx, number, announcement.text).await;
+            }
+            AdhocTarget::Call {
+                recipients,
+                transmitter_groups,
+            } => {
+                let transmitter_groups = transmitter_groups
+                    .unwrap_or_else(|| ctx.call_settings.transmitter_groups.clone());
+                send_call(ctx, &announcement.text, &recipients, &transmitter_groups).await;
+            }
+        }
+    }
+}
+
+/// Sends news written by the operator (rather than built from an event) to a slot of the main
+/// rubric.
+async fn send_operator_news(ctx: &Context, number: i8, text: String) {
+    let news = RubricNews {
+        rubric: ctx.news_settings.rubric.clone(),
+        number: Some(number),
+        text,
+    };
+    send_news(ctx, &news).await;
+}
+
+/// Flags the next announcement as overdue if it has not been attempted within the grace period
+/// of when it was due.
+async fn check_next_announcement_overdue(
+    ctx: &Context,
+    last_attempt: DateTime<Utc>,
+    grace: Duration,
+) {
+    match ctx.plan.next_announcement_after(last_attempt).await {
+        Ok(next) => {
+            let overdue = next.is_some_and(|next| ctx.clock.now() > next + grace);
+            if overdue {
+                warn!("Announcement due at {next:?} has not been attempted");
+            }
+
+            gauge!("next_announcement_timestamp_seconds")
+                .set(next.map_or(0.0, |next| next.timestamp() as f64));
+            gauge!("next_announcement_overdue").set(if overdue { 1.0 } else { 0.0 });
+        }
+        Err(e) => {
+            warn!("Failed to check for overdue announcements: {e}");
+        }
+    }
+}
+
+/// Checks if news may be written to its rubric slot, which is not the case if imminent news
+/// protection is enabled and the slot holds news for an event that starts sooner.
+fn may_overwrite_slot(
+    ctx: &Context,
+    state: &AnnouncementState,
+    news: &RubricNews,
+    event_start: DateTime<Utc>,
+) -> bool {
+    if !ctx.cli.protect_imminent_news {
+        return true;
+    }
+
+    match state
+        .slot_occupancy
+        .more_imminent_occupant(news, event_start, ctx.schedule_now())
+    {
+        Some(occupant_start) => {
+            info!("Rubric slot holds news for an event starting at {occupant_start}, not overwriting it");
+            counter!("dapnet_news_overwrites_prevented").increment(1);
+            false
+        }
+        None => true,
+    }
+}
+
+/// Fetches the schedule to look for changes to announce or report to the operator.
+async fn check_schedule(ctx: &Context, state: &mut AnnouncementState) {
+    let events = match ctx.plan.schedule.events().await {
+        Ok(events) => events,
+        Err(e) => {
+            warn!("Failed to fetch schedule to check for changes: {e}");
+            return;
+        }
+    };
+
+    if ctx.cli.announce_speaker_changes {
+        announce_speaker_changes(ctx, state, &events).await;
+    }
+
+    if ctx.cli.page_schedule_changes || ctx.cli.announce_schedule_changes {
+        if let Some(changes) = state.schedule_changes.update(&events) {
+            if !changes.is_empty() {
+                info!("Schedule changed: {}", changes.summary());
+
+                if ctx.cli.announce_schedule_changes {
+                    announce_schedule_changes(ctx, state, &changes).await;
+                }
+
+                if ctx.cli.page_schedule_changes {
+                    page_operator(ctx, &changes.summary()).await;
+                }
+            }
+        }
+    }
+}
+
+/// Sends news correcting earlier announcements of events that have been cancelled or moved.
+///
+/// Only events that have already been announced, or that were or are now due to start within
+/// the horizon, are announced, so that changes to events far in the future do not fill the
+/// rubric.
+async fn announce_schedule_changes(
+    ctx: &Context,
+    state: &mut AnnouncementState,
+    changes: &ScheduleChanges,
+) {
+    let now = ctx.schedule_now();
+    let horizon = now + Duration::minutes(ctx.cli.schedule_change_horizon);
+    let is_relevant = |id: u32, starts: &[DateTime<FixedOffset>]| {
+        state.announced_events.contains(id)
+            || starts.iter().any(|start| {
+                let start = start.with_timezone(&Utc);
+                start >= now && start <= horizon
+            })
+    };
+
+    let mut corrections = Vec::new();
+
+    for event in &changes.removed {
+        if is_relevant(event.id, &[event.start]) {
+            info!("Event {} has been cancelled", event.id);
+            counter!("schedule_change_announcements", "change" => "cancelled").increment(1);
+            corrections.push((event.to_cancellation_news(&ctx.news_settings), event.start));
+        }
+    }
+
+    for moved in &changes.moved {
+        let event = &moved.event;
+        if is_relevant(event.id, &[event.start, moved.previous_start]) {
+            info!(
+                "Event {} has moved from {} at {} to {} at {}",
+                event.id, moved.previous_venue, moved.previous_start, event.venue, event.start
+            );
+            counter!("schedule_change_announcements", "change" => "moved").increment(1);
+            corrections.push((
+                event.to_move_news(
+                    &ctx.news_settings,
+                    &moved.previous_venue,
+                    moved.previous_start,
+                ),
+                moved.previous_start,
+            ));
+        }
+    }
+
+    for (news, event_start) in corrections {
+        if let Some(news) = news {
+            send_correction_news(ctx, state, &news, event_start.with_timezone(&Utc)).await;
+        }
+    }
+}
+
+async fn announce_speaker_changes(ctx: &Context, state: &mut AnnouncementState, events: &[Event]) {
+    let changed = state
+        .announced_events
+        .speaker_changes(events, ctx.schedule_now());
+    if !changed.is_empty() {
+        save_announced_events(ctx, state);
+    }
+
+    for event in changed {
+        if let Some(news) = event.to_speaker_change_news(&ctx.news_settings) {
+            info!("Speaker changed for event {}: {:?}", event.id, news);
+            send_correction_news(ctx, state, &news, event.start.with_timezone(&Utc)).await;
+        }
+    }
+}
+
+/// Writes the announced events to the state file, if there is one.
+///
+/// Nothing is written in a dry run, so that a later live run using the same state file does not
+/// skip events that were never sent.
+fn save_announced_events(ctx: &Context, state: &AnnouncementState) {
+    if ctx.cli.dry_run {
+        return;
+    }
+
+    if let Some(path) = &ctx.cli.state_file {
+        if let Err(e) = state.announced_events.save(path) {
+            warn!("Failed to save announced events to {}: {e}", path.display());
+        }
+    }
+}
+
+/// Sends news correcting an earlier announcement, unless it would overwrite news for a more
+/// imminent event or duplicates news that was recently sent.
+async fn send_correction_news(
+    ctx: &Context,
+    state: &mut AnnouncementState,
+    news: &RubricNews,
+    event_start: DateTime<Utc>,
+) {
+    if !may_overwrite_slot(ctx, state, news, event_start) {
+        return;
+    }
+    let target = format!("{}/{:?}", news.rubric, news.number);
+    if is_duplicate(ctx, state, &target, &news.text) {
+        return;
+    }
+    state.slot_occupancy.record(news, event_start);
+
+    send_news(ctx, news).await;
+}
+
+async fn check_announcement_watchdog(
+    ctx: &Context,
+    watchdog: &mut AnnouncementWatchdog,
+    event_start_offset: Duration,
+) {
+    let now = ctx.schedule_now();
+
+    if !watchdog.is_overdue(now) {
+        return;
+    }
+
+    let events = match ctx.plan.schedule.events().await {
+        Ok(events) => events,
+        Err(e) => {
+            warn!("Failed to fetch schedule for announcement watchdog: {e}");
+            return;
+        }
+    };
+
+    let window_start = now - watchdog.window();
+    let due_announcements = events
+        .iter()
+        .map(|e| e.start.with_timezone(&Utc) + event_start_offset)
+        .filter(|t| *t > window_start && *t <= now)
+        .count();
+
+    if due_announcements == 0 {
+        return;
+    }
+
+    error!(
+        "No announcements sent since {window_start} despite {due_announcements} being due, paging operator"
+    );
+    gauge!("announcement_watchdog_triggered").set(1.0);
+    watchdog.record_alerted();
+
+    page_operator(
+        ctx,
+        &format!(
+            "EMF anncr: no announcements sent in {}h",
+            watchdog.window().num_hours()
+        ),
+    )
+    .await;
+}
+
+async fn track_schedule_outage(
+    ctx: &Context,
+    tracker: &mut OutageTracker,
+    msg: &emfcamp_schedule_api::Result<AnnouncerPollResult>,
+) {
+    let now = ctx.clock.now();
+
+    match msg {
+        Err(_) => {
+            if let Some(duration) = tracker.record_failure(now) {
+                error!("Schedule API has been failing for {duration}, paging operator");
+
+                page_operator(
+                    ctx,
+                    &format!(
+                        "EMF anncr: schedule API failing for {}m",
+                        duration.num_minutes()
+                    ),
+                )
+                .await;
+            }
+        }
+        Ok(AnnouncerPollResult::Event(_)) => {}
+        Ok(_) => {
+            if let Some(duration) = tracker.record_success(now) {
+                info!("Schedule API recovered after {duration}");
+            }
+        }
+    }
+}
+
+/// Tidies up before stopping, saving the announced events and letting the operator know.
+///
+/// Announcements are sent from the main loop, so any that was being sent when the signal to stop
+/// arrived has finished by the time this is called. News that could not be delivered earlier is
+/// given one last try, anything still unsent (including all that is held back while sending is
+/// paused) is dropped and the count included in the shutdown page.
+async fn shutdown(ctx: &Context, state: &AnnouncementState, reason: &str) {
+    info!("Stopping ({reason})");
+
+    send_undelivered_news(ctx).await;
+
+    let dropped = ctx.undelivered.lock().unwrap().len()
+        + ctx.paused.lock().unwrap().as_ref().map_or(0, Vec::len);
+
+    let reason = if dropped > 0 {
+        warn!("{dropped} queued news and calls will not be sent");
+        format!("{reason}, {dropped} unsent dropped")
+    } else {
+        reason.to_string()
+    };
+
+    save_announced_events(ctx, state);
+
+    if !ctx.cli.dry_run {
+        startup::send_shutdown_page(
+            &ctx.dapnet,
+            &ctx.call_settings,
+            &ctx.startup_page_settings,
+            &reason,
+            ctx.clock.now(),
+        )
+        .await;
+    }
+}
+
+/// News or a call held back while sending is paused.
+enum PausedSend {
+    News(RubricNews),
+    Call {
+        text: String,
+        recipients: Vec<String>,
+        transmitter_groups: Vec<String>,
+    },
+}
+
+/// Holds news or a call back if sending is paused, returning true if it was.
+fn queue_if_paused(ctx: &Context, send: impl FnOnce() -> PausedSend) -> bool {
+    match ctx.paused.lock().unwrap().as_mut() {
+        Some(queue) => {
+            queue.push(send());
+            true
+        }
+        None => false,
+    }
+}
+
+/// Pauses sending news and calls if it is not paused, otherwise resumes it and sends everything
+/// queued while it was paused.
+async fn toggle_pause(ctx: &Context) {
+    let queued = {
+        let mut paused = ctx.paused.lock().unwrap();
+        match paused.take() {
+            Some(queued) => queued,
+            None => {
+                warn!("Sending paused, news and calls will be queued until resumed");
+                gauge!("sending_paused").set(1.0);
+                *paused = Some(Vec::new());
+                return;
+            }
+        }
+    };
+
+    info!(
+        "Sending resumed, sending {} queued news and calls",
+        queued.len()
+    );
+    gauge!("sending_paused").set(0.0);
+
+    for send in queued {
+        match send {
+            PausedSend::News(news) => {
+                send_news(ctx, &news).await;
+            }
+            PausedSend::Call {
+                text,
+                recipients,
+                transmitter_groups,
+            } => {
+                send_call(ctx, &text, &recipients, &transmitter_groups).await;
+            }
+        }
+    }
+}
+
+/// What became of news or a call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SendOutcome {
+    /// Accepted by DAPNET
+    Sent,
+
+    /// Recorded instead of sent, as this is a dry run
+    DryRun,
+
+    /// Held back to be sent later
+    Queued,
+
+    Failed,
+}
+
+impl SendOutcome {
+    /// Whether the announcement went out, or would have done were this not a dry run.
+    fn is_announced(self) -> bool {
+        matches!(self, Self::Sent | Self::DryRun)
+    }
+
+    /// Name of the outcome, used in metric labels
+    fn label(self) -> &'static str {
+        match self {
+            Self::Sent => "ok",
+            Self::DryRun => "dry_run",
+            Self::Queued => "queued",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+/// Sends news, or records it in the dry run output in a dry run.
+///
+/// Every news item goes through here, so this is the only place dry runs are handled for news.
+async fn send_news(ctx: &Context, news: &RubricNews) -> SendOutcome {
+    ctx.health.record_activity();
+
+    if ctx.cli.dry_run {
+        info!("Dry run, not sending news");
+        if let Some(path) = &ctx.cli.dry_run_output {
+            if let Err(e) = dry_run::record_news(path, news) {
+                warn!("Failed to write dry run output: {e}");
+            }
+        }
+        return SendOutcome::DryRun;
+    }
+
+    if queue_if_paused(ctx, || PausedSend::News(news.clone())) {
+        info!("Sending is paused, queueing news");
+        counter!("dapnet_event_announcements", "result" => "queued").increment(1);
+        return SendOutcome::Queued;
+    }
+
+    let outgoing = match news.to_outgoing() {
+        Ok(outgoing) => outgoing,
+        Err(e) => {
+            error!("Failed to build news: {e}");
+            return SendOutcome::Failed;
+        }
+    };
+
+    let mut verify_attempts = 0;
+
+    loop {
+        if !ctx.dapnet_health.lock().unwrap().allow(ctx.clock.now()) {
+            warn!("DAPNET circuit breaker is open, queueing news");
+            counter!("dapnet_event_announcements", "result" => "skipped").increment(1);
+            ctx.activity.publish(Activity::Failed {
+                news: news.clone(),
+                error: "DAPNET circuit breaker is open".to_string(),
+            });
+            queue_undelivered_news(ctx, news);
+            return SendOutcome::Queued;
+        }
+
+        if let Some(address) = &ctx.cli.dapnet_connectivity_check {
+            if !connectivity::is_reachable(address, std::time::Duration::from_secs(5)).await {
+                warn!("DAPNET is unreachable, queueing news");
+                counter!("dapnet_event_announcements", "result" => "unreachable").increment(1);
+                ctx.dapnet_health
+                    .lock()
+                    .unwrap()
+                    .record_failure(ctx.clock.now());
+                ctx.activity.publish(Activity::Failed {
+                    news: news.clone(),
+                    error: "DAPNET is unreachable".to_string(),
+                });
+                queue_undelivered_news(ctx, news);
+                return SendOutcome::Queued;
+            }
+        }
+
+        match retry::send(&ctx.call_settings.retry, "news", || {
+            ctx.dapnet.new_news(&outgoing)
+        })
+        .await
+        {
+            Ok(_) => {
+                info!("News sent");
+                counter!("dapnet_event_announcements", "result" => "ok").increment(1);
+                ctx.dapnet_health
+                    .lock()
+                    .unwrap()
+                    .record_success(ctx.clock.now());
+                ctx.activity.publish(Activity::Sent { news: news.clone() });
+                ping_healthcheck(ctx);
+
+                // Anything still waiting for this slot is older, so must not overwrite this
+                let mut undelivered = ctx.undelivered.lock().unwrap();
+                undelivered.retain(|queued| {
+                    (&queued.rubric, queued.number) != (&news.rubric, news.number)
+                });
+                gauge!("dapnet_undelivered_news").set(undelivered.len() as f64);
+            }
+            Err(e) => {
+                error!("Failed to send news: {e}");
+                counter!("dapnet_event_announcements", "result" => "error").increment(1);
+                ctx.dapnet_health
+                    .lock()
+                    .unwrap()
+                    .record_failure(ctx.clock.now());
+                ctx.activity.publish(Activity::Failed {
+                    news: news.clone(),
+                    error: e.to_string(),
+                });
+                return SendOutcome::Failed;
+            }
+        }
+
+        if !ctx.cli.verify_news {
+            return SendOutcome::Sent;
+        }
+
+        if news_verify::news_is_present(&ctx.dapnet, news).await {
+            counter!("dapnet_news_verifications", "result" => "ok").increment(1);
+            return SendOutcome::Sent;
+        }
+
+        counter!("dapnet_news_verifications", "result" => "mismatch").increment(1);
+
+        if verify_attempts >= ctx.cli.verify_news_retries {
+            warn!("News could not be verified, giving up");
+            return SendOutcome::Sent;
+        }
+
+        verify_attempts += 1;
+        warn!(
+            "Resending news after failed verification (attempt {}/{})",
+            verify_attempts, ctx.cli.verify_news_retries
+        );
+    }
+}
+
+/// Holds news back to be sent once DAPNET can be reached, replacing any news already waiting for
+/// the same rubric slot as it would be overwritten anyway.
+fn queue_undelivered_news(ctx: &Context, news: &RubricNews) {
+    let mut undelivered = ctx.undelivered.lock().unwrap();
+    undelivered.retain(|queued| (&queued.rubric, queued.number) != (&news.rubric, news.number));
+    undelivered.push(news.clone());
+    gauge!("dapnet_undelivered_news").set(undelivered.len() as f64);
+}
+
+/// Sends the news that could not be sent earlier, once the DAPNET circuit breaker allows it.
+///
+/// Stops at the first news that cannot be sent, keeping the rest waiting, so that an outage that
+/// is not over does not hold up the main loop with a check for each of them.
+async fn send_undelivered_news(ctx: &Context) {
+    // Sending it now would only move it to the paused queue
+    if ctx.paused.lock().unwrap().is_some() {
+        return;
+    }
+
+    if !ctx.dapnet_health.lock().unwrap().allow(ctx.clock.now()) {
+        return;
+    }
+
+    let undelivered = std::mem::take(&mut *ctx.undelivered.lock().unwrap());
+    if undelivered.is_empty() {
+        return;
+    }
+
+    info!(
+        "Sending {} news that could not be sent earlier",
+        undelivered.len()
+    );
+
+    let mut undelivered = undelivered.into_iter();
+    for news in undelivered.by_ref() {
+        if send_news(ctx, &news).await == SendOutcome::Queued {
+            break;
+        }
+    }
+
+    for news in undelivered {
+        queue_undelivered_news(ctx, &news);
+    }
+
+    gauge!("dapnet_undelivered_news").set(ctx.undelivered.lock().unwrap().len() as f64);
+}
+
+/// Sends a call, or records it in the dry run output in a dry run.
+///
+/// Every call made while running goes through here (the startup and shutdown pages are sent
+/// directly as they check the DAPNET connection rather than announce anything), so this is the
+/// only place dry runs are handled for calls.
+async fn send_call(
+    ctx: &Context,
+    text: &str,
+    recipients: &[String],
+    transmitter_groups: &[String],
+) -> SendOutcome {
+    ctx.health.record_activity();
+
+    if ctx.cli.dry_run {
+        info!("Dry run, not sending call to {}", recipients.join(", "));
+        if let Some(path) = &ctx.cli.dry_run_output {
+            let result = call::prepare_call(&ctx.call_settings, text, recipients)
+                .and_then(|call| dry_run::record_call(path, &call, transmitter_groups));
+            if let Err(e) = result {
+                warn!("Failed to write dry run output: {e}");
+            }
+        }
+        return SendOutcome::DryRun;
+    }
+
+    if queue_if_paused(ctx, || PausedSend::Call {
+        text: text.to_string(),
+        recipients: recipients.to_vec(),
+        transmitter_groups: transmitter_groups.to_vec(),
+    }) {
+        info!(
+            "Sending is paused, queueing call to {}",
+            recipients.join(", ")
+        );
+        return SendOutcome::Queued;
+    }
+
+    match call::send_call(
+        &ctx.dapnet,
+        &ctx.call_settings,
+        text,
+        recipients,
+        transmitter_groups,
+    )
+    .await
+    {
+        Ok(()) => {
+            ping_healthcheck(ctx);
+            SendOutcome::Sent
+        }
+        Err(e) => {
+            warn!("Failed to send call to {}: {e}", recipients.join(", "));
+            SendOutcome::Failed
+        }
+    }
+}
+
+async fn send_canary_page(ctx: &Context, callsign: &str, text: &str) {
+    send_call(
+        ctx,
+        text,
+        &[callsign.to_string()],
+        &ctx.call_settings.transmitter_groups,
+    )
+    .await;
+}
+
+async fn page_operator(ctx: &Context, text: &str) {
+    let outcome = send_call(
+        ctx,
+        text,
+        &[ctx.cli.operator_callsign.clone()],
+        &ctx.call_settings.transmitter_groups,
+    )
+    .await;
+
+    if outcome == SendOutcome::Failed {
+        error!("Failed to page operator");
+    }
+}
+
+/// Sends "now & next" news for each venue, skipping any that has not changed since it was last
+/// sent.
+async fn send_now_next(ctx: &Context, state: &mut AnnouncementState, rubric: &str) {
+    // Subscribers to the now and next rubric must not be paged in shadow mode either
+    let rubric = ctx.cli.shadow_rubric.as_deref().unwrap_or(rubric);
+
+    let events = match ctx.plan.schedule.events().await {
+        Ok(events) => ctx.plan.announceable(events),
+        Err(e) => {
+            warn!("Failed to fetch schedule for now and next: {e}");
+            return;
+        }
+    };
+
+    for news in now_next::build_now_next(&events, ctx.schedule_now(), &ctx.news_settings, rubric) {
+        let target = format!("{}/{:?}", news.rubric, news.number);
+        if is_duplicate(ctx, state, &target, &news.text) {
+            continue;
+        }
+
+        info!("Sending now and next: {:?}", news);
+        counter!("now_next_announcements").increment(1);
+
+        send_news(ctx, &news).await;
+    }
+}
+
+/// Sends the digest of the day's events if it is due.
+async fn send_digest(ctx: &Context, digest_scheduler: &mut DigestScheduler) {
+    if digest_scheduler.needs_offset() {
+        match ctx.plan.schedule.events().await {
+            Ok(events) => digest_scheduler.learn_offset(&events),
+            Err(e) => {
+                warn!("Failed to fetch schedule to find its time zone for the daily digest: {e}");
+                return;
+            }
+        }
+    }
+
+    let Some(date) = digest_scheduler.due(ctx.schedule_now()) else {
+        return;
+    };
+
+    let events = match ctx.plan.schedule.events().await {
+        Ok(events) => ctx.plan.announceable(events),
+        Err(e) => {
+            warn!("Failed to fetch schedule for the daily digest: {e}");
+            return;
+        }
+    };
+
+    digest_scheduler.learn_offset(&events);
+    digest_scheduler.record_sent(date);
+
+    let Some(text) = digest::build_digest(
+        &events,
+        date,
+        &ctx.news_settings,
+        &ctx.plan.tiers,
+        ctx.news_settings.max_length,
+    ) else {
+        info!("No events to send a digest for on {date}");
+        return;
+    };
+    let text = ctx.news_settings.charset.sanitise(text);
+
+    info!("Sending daily digest: {text}");
+    counter!("daily_digests").increment(1);
+
+    send_operator_news(ctx, ctx.cli.digest_news_number, text).await;
+}
+
+async fn send_heartbeat_page(ctx: &Context, callsign: &str) {
+    let text = format!("EMF anncr alive {}", ctx.clock.now().format("%H:%M"));
+    info!("Sending heartbeat page: {text}");
+
+    send_call(
+        ctx,
+        &text,
+        &[callsign.to_string()],
+        &ctx.call_settings.transmitter_groups,
+    )
+    .await;
+}
+
+/// Pings the healthcheck URL, if there is one, to show that the announcer is making progress.
+///
+/// This is done in the background so that a slow healthcheck service does not hold up
+/// announcements.
+fn ping_healthcheck(ctx: &Context) {
+    let Some(url) = ctx.cli.healthcheck_ping_url.clone() else {
+        return;
+    };
+    let http_client = ctx.http_client.clone();
+
+    tokio::spawn(async move {
+        let result = http_client
+            .get(url)
+            .timeout(std::time::Duration::from_secs(10))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status());
+
+        if let Err(e) = result {
+            warn!("Failed to ping healthcheck: {e}");
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::future::LocalBoxFuture;
+
+    /// Keeps the text of each announcement it is given.
+    #[derive(Default)]
+    struct RecordingNotifier {
+        sent: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Notifier for RecordingNotifier {
+        fn name(&self) -> &str {
+            "recording"
+        }
+
+        fn accepts(&self, _event: &Event, _delivery: Delivery) -> bool {
+            true
+        }
+
+        fn prepare(
+            &self,
+            _ctx: &Context,
+            _state: &mut AnnouncementState,
+            _kind: AnnouncementKind,
+            _announcement: &Announcement,
+            _event_start: DateTime<Utc>,
+        ) -> bool {
+            true
+        }
+
+        fn notify<'a>(
+            &'a self,
+            _ctx: &'a Context,
+            announcement: &'a Announcement,
+        ) -> LocalBoxFuture<'a, SendOutcome> {
+            self.sent
+                .lock()
+                .unwrap()
+                .push(announcement.news.text.clone());
+            Box::pin(async { SendOutcome::Sent })
+        }
+    }
+
+    fn context(args: &[&str], clock: Arc<MockClock>) -> (Context, Arc<Mutex<Vec<String>>>) {
+        let cli = Cli::parse_from(
+            [
+                "announcer",
+                "--dapnet-username=test",
+                "--dapnet-password=test",
+            ]
+            .iter()
+            .chain(args),
+        );
+
+        let news_settings = build_news_settings(&cli, None, clock.clone()).unwrap();
+        let plan = Arc::new(build_plan(&cli).unwrap());
+        let health = Arc::new(Health::new(std::time::Duration::from_secs(60)));
+        let mut ctx = build_context(
+            cli,
+            clock,
+            ActivityFeed::new(),
+            plan,
+            Dapnet::new("", ""),
+            news_settings,
+            health,
+        )
+        .unwrap();
+
+        let notifier = RecordingNotifier::default();
+        let sent = notifier.sent.clone();
+        ctx.notifiers = vec![Box::new(notifier)];
+
+        (ctx, sent)
+    }
+
+    fn event(id: u32, start: &str, end: &str) -> Event {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "start_date": start,
+            "end_date": end,
+            "venue": "Stage A",
+            "title": "Opening ceremony",
+            "speaker": "",
+            "description": "",
+            "type": "talk",
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn announces_using_the_time_from_the_clock() {
+        let clock = Arc::new(MockClock::new("2024-05-30T13:50:00Z".parse().unwrap()));
+        let (ctx, sent) = context(&["--relative-start-time"], clock.clone());
+        let mut state = AnnouncementState::new(&ctx).unwrap();
+        let event = event(1, "2024-05-30T14:00:00+00:00", "2024-05-30T15:00:00+00:00");
+
+        let announced = handle_announcer_event(
+            &ctx,
+            &mut state,
+            AnnouncementKind::Upcoming,
+            Ok(AnnouncerPollResult::Event(event.clone())),
+        )
+        .await;
+        assert!(announced);
+        assert_eq!(
+            *sent.lock().unwrap(),
+            ["<Stg A> in 10 min: Opening ceremony"]
+        );
+
+        // The event is only announced once, however often the announcer gives it
+        clock.set("2024-05-30T13:58:00Z".parse().unwrap());
+        let announced = handle_announcer_event(
+            &ctx,
+            &mut state,
+            AnnouncementKind::Upcoming,
+            Ok(AnnouncerPollResult::Event(event)),
+        )
+        .await;
+        assert!(!announced);
+        assert_eq!(sent.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn transliterated_titles_fit_the_maximum_length() {
+        let titles = [
+            (
+                "Café Scientifique: Ångström-scale Électronique",
+                "Zoë Müller",
+                "<Stg A> Cafe Scientifique:...",
+            ),
+            (
+                "\u{201c}Hacking\u{201d} the Planet \u{1f30d}\u{1f680} \u{2013} Live!",
+                "Siân O\u{2019}Brien",
+                "<Stg A> \"Hacking\" the Planet earth...",
+            ),
+            (
+                "日本のはんだ付け入門 (Soldering for Beginners)",
+                "山田 太郎",
+                "<Stg A> Ri Ben nohandaFu keRu Men...",
+            ),
+        ];
+
+        for (id, (title, speaker, expected)) in (1..).zip(titles) {
+            let clock = Arc::new(MockClock::new("2024-05-30T13:50:00Z".parse().unwrap()));
+            let (ctx, sent) = context(&["--transliterate", "--max-news-length=40"], clock);
+            let mut state = AnnouncementState::new(&ctx).unwrap();
+            let mut event = event(id, "2024-05-30T14:00:00+00:00", "2024-05-30T15:00:00+00:00");
+            event.title = title.to_string();
+            event.speaker = speaker.to_string();
+
+            let announced = handle_announcer_event(
+                &ctx,
+                &mut state,
+                AnnouncementKind::Upcoming,
+                Ok(AnnouncerPollResult::Event(event)),
+            )
+            .await;
+            assert!(announced);
+
+            let sent = sent.lock().unwrap();
+            assert_eq!(*sent, [expected]);
+            assert!(sent[0].chars().all(|c| c == ' ' || c.is_ascii_graphic()));
+            assert!(sent[0].chars().count() <= 40);
+        }
+    }
+
+    #[test]
+    fn shadow_mode_does_not_call_subscribers() {
+        let notifier_names = |args: &[&str]| {
+            let cli = Cli::parse_from(
+                [
+                    "announcer",
+                    "--dapnet-username=test",
+                    "--dapnet-password=test",
+                ]
+                .iter()
+                .chain(args),
+            );
+            let clock = Arc::new(MockClock::new(Utc::now()));
+            let news_settings = build_news_settings(&cli, None, clock).unwrap();
+
+            build_notifiers(&cli, &news_settings)
+                .iter()
+                .map(|notifier| notifier.name().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            notifier_names(&["--call-target=crew=M0AAA", "--shadow-rubric=test"]),
+            ["rubric"]
+        );
+        assert_eq!(
+            notifier_names(&[
+                "--call-target=crew=M0AAA",
+                "--shadow-rubric=test",
+                "--shadow-call-recipients=M0BBB"
+            ]),
+            ["rubric", "crew"]
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use clap::ValueEnum;
+
+/// What to do with a message that is longer than a pager can show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum OverlongPolicy {
+    /// Cut the message short, ending it with "..."
+    Truncate,
+    /// Send the message as several numbered parts (e.g. "1/2 ...", "2/2 ...")
+    Split,
+}
+
+/// Words (with the separator before them) that can be dropped along with a part of a message,
+/// so that "Title by Speaker" becomes "Title" rather than "Title by".
+const SEPARATORS: [&str; 6] = [" by", " with", " -", " |", ",", ":"];
+
+/// Fits a message within the maximum length according to the policy, returning the text of each
+/// message to send.
+pub(crate) fn fit(text: &str, max_length: usize, policy: OverlongPolicy) -> Vec<String> {
+    match policy {
+        OverlongPolicy::Truncate => vec![shorten(text, max_length, &[])],
+        OverlongPolicy::Split => split(text, max_length),
+    }
+}
+
+/// Shortens a message to fit within the maximum length.
+///
+/// Each of the droppable parts (e.g. the speaker) is removed in turn until the message fits, then
+/// if it is still too long it is cut short at a word boundary where possible and ended with "...".
+pub(crate) fn shorten(text: &str, max_length: usize, droppable: &[&str]) -> String {
+    let mut text = text.to_string();
+
+    for part in droppable.iter().map(|p| p.trim()).filter(|p| !p.is_empty()) {
+        if fits(&text, max_length) {
+            break;
+        }
+        text = remove_part(&text, part);
+    }
+
+    truncate(&text, max_length)
+}
+
+/// Splits a message into parts that each fit within the maximum length, numbered "1/n", "2/n" and
+/// so on. Messages that already fit are returned unchanged.
+pub(crate) fn split(text: &str, max_length: usize) -> Vec<String> {
+    if fits(text, max_length) {
+        return vec![text.to_string()];
+    }
+
+    // The width of the part numbers depends on how many parts there are, so keep increasing the
+    // number of parts allowed for until the text fits in that many
+    for count in 2.. {
+        let prefix_length = format!("{count}/{count} ").chars().count();
+
+        if prefix_length >= max_length {
+            return vec![truncate(text, max_length)];
+        }
+
+        let parts = wrap(text, max_length - prefix_length);
+
+        if parts.len() <= count {
+            let count = parts.len();
+            return parts
+                .into_iter()
+                .enumerate()
+                .map(|(i, part)| format!("{}/{count} {part}", i + 1))
+                .collect();
+        }
+    }
+
+    unreachable!()
+}
+
+fn fits(text: &str, max_length: usize) -> bool {
+    text.chars().count() <= max_length
+}
+
+/// Removes the first occurrence of a part from the text, along with any separator before it and
+/// brackets around it.
+fn remove_part(text: &str, part: &str) -> String {
+    let Some(start) = text.find(part) else {
+        return text.to_string();
+    };
+
+    let mut before = text[..start].trim_end();
+    let mut after = &text[start + part.len()..];
+
+    if let (Some(b), Some(a)) = (before.strip_suffix('('), after.strip_prefix(')')) {
+        before = b.trim_end();
+        after = a;
+    }
+
+    let before = SEPARATORS
+        .iter()
+        .find_map(|separator| before.strip_suffix(separator))
+        .unwrap_or(before)
+        .trim_end();
+
+    let after = after.trim_start_matches([',', ';']);
+
+    format!("{before}{after}").trim().to_string()
+}
+
+/// Cuts a message down to the maximum length, ending it with "..." if there is room for it.
+fn truncate(text: &str, max_length: usize) -> String {
+    if fits(text, max_length) {
+        return text.to_string();
+    }
+
+    if max_length < 3 {
+        return text.chars().take(max_length).collect();
+    }
+
+    let limit = max_length - 3;
+    let cut: String = text.chars().take(limit).collect();
+
+    // Prefer to cut between words, unless that would lose too much of the message
+    let cut = match cut.rfind(char::is_whitespace) {
+        Some(i) if cut[..i].chars().count() >= limit / 2 => &cut[..i],
+        _ => cut.as_str(),
+    };
+
+    format!("{}...", cut.trim_end())
+}
+
+/// Wraps text into lines no longer than the width, breaking between words and splitting words
+/// that are longer than a whole line.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+
+    for word in text.split_whitespace() {
+        let mut word: Vec<char> = word.chars().collect();
+
+        while word.len() > width {
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            lines.push(word.drain(..width).collect());
+        }
+
+        if word.is_empty() {
+            continue;
+        }
+
+        let needed = if line.is_empty() {
+            word.len()
+        } else {
+            line.chars().count() + 1 + word.len()
+        };
+
+        if needed > width {
+            lines.push(std::mem::take(&mut line));
+        }
+
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.extend(word);
+    }
+
+    if !line.is_empty() {
+        lines.push(line);
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_of_the_maximum_length_is_unchanged() {
+        assert_eq!(shorten("0123456789", 10, &[]), "0123456789");
+    }
+
+    #[test]
+    fn text_one_over_the_maximum_length_is_truncated() {
+        let text = shorten("0123456789A", 10, &[]);
+
+        assert_eq!(text, "0123456...");
+        assert_eq!(text.chars().count(), 10);
+    }
+
+    #[test]
+    fn speaker_is_dropped_before_the_title_is_truncated() {
+        assert_eq!(
+            shorten("Stage A: A Talk by Some Speaker", 20, &["Some Speaker"]),
+            "Stage A: A Talk"
+        );
+    }
+
+    #[test]
+    fn maximum_length_too_short_for_an_ellipsis() {
+        for max_length in 0..3 {
+            let text = shorten("A Talk", max_length, &[]);
+
+            assert_eq!(text, "A Talk"[..max_length]);
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use crate::{
+    activity::{Activity, ActivityFeed},
+    clock::Clock,
+    plan::{AnnouncementPlan, PlannedAnnouncement},
+};
+use chrono::{DateTime, Duration, Local, Utc};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout},
+    widgets::{Block, List, Paragraph},
+    DefaultTerminal, Frame,
+};
+use std::{collections::VecDeque, sync::Arc};
+use tokio::{
+    runtime::Handle,
+    sync::{
+        broadcast::{self, error::TryRecvError},
+        mpsc::UnboundedSender,
+    },
+    task::JoinHandle,
+};
+use tracing::error;
+
+/// Number of pending announcements shown.
+const PENDING_ANNOUNCEMENTS: usize = 20;
+
+/// Number of recent sends kept.
+const RECENT_SENDS: usize = 50;
+
+/// Something the operator asked for from the monitor.
+#[derive(Debug)]
+pub(crate) enum MonitorCommand {
+    /// Send news to a slot of the main rubric
+    SendNews { number: i8, text: String },
+    /// Stop the announcer
+    Quit,
+}
+
+/// Terminal UI showing the pending announcements, recent sends and DAPNET health, with a prompt
+/// for sending news by hand.
+struct Monitor {
+    plan: Arc<AnnouncementPlan>,
+    clock: Arc<dyn Clock>,
+    commands: UnboundedSender<MonitorCommand>,
+
+    pending: Vec<PlannedAnnouncement>,
+    pending_refreshed: Option<DateTime<Utc>>,
+    recent: VecDeque<String>,
+
+    consecutive_failures: usize,
+    last_error: Option<String>,
+
+    input: String,
+    status: String,
+}
+
+/// Runs the monitor on its own thread until the operator quits, at which point
+/// [`MonitorCommand::Quit`] is sent.
+pub(crate) fn spawn(
+    plan: Arc<AnnouncementPlan>,
+    clock: Arc<dyn Clock>,
+    activity: &ActivityFeed,
+    commands: UnboundedSender<MonitorCommand>,
+) -> JoinHandle<()> {
+    let mut activity = activity.subscribe();
+    let runtime = Handle::current();
+
+    tokio::task::spawn_blocking(move || {
+        let mut monitor = Monitor {
+            plan,
+            clock,
+            commands,
+            pending: Vec::new(),
+            pending_refreshed: None,
+            recent: VecDeque::new(),
+            consecutive_failures: 0,
+            last_error: None,
+            input: String::new(),
+            status: "Enter \"<slot> <text>\" to send news, Esc to quit".to_string(),
+        };
+
+        let mut terminal = ratatui::init();
+        let result = monitor.run(&mut terminal, &runtime, &mut activity);
+        ratatui::restore();
+
+        if let Err(e) = result {
+            error!("Monitor failed: {e}");
+        }
+
+        let _ = monitor.commands.send(MonitorCommand::Quit);
+    })
+}
+
+impl Monitor {
+    fn run(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        runtime: &Handle,
+        activity: &mut broadcast::Receiver<Activity>,
+    ) -> std::io::Result<()> {
+        loop {
+            if self
+                .pending_refreshed
+                .is_none_or(|t| self.clock.now() - t >= Duration::minutes(1))
+            {
+                self.refresh_pending(runtime);
+            }
+
+            loop {
+                match activity.try_recv() {
+                    Ok(activity) => self.record(activity),
+                    Err(TryRecvError::Lagged(_)) => {}
+                    Err(_) => break,
+                }
+            }
+
+            terminal.draw(|frame| self.draw(frame))?;
+
+            if !event::poll(std::time::Duration::from_millis(250))? {
+                continue;
+            }
+
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+
+                match key.code {
+                    KeyCode::Esc => return Ok(()),
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        return Ok(())
+                    }
+                    KeyCode::Enter => self.submit(),
+                    KeyCode::Backspace => {
+                        self.input.pop();
+                    }
+                    KeyCode::Char(c) => self.input.push(c),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn refresh_pending(&mut self, runtime: &Handle) {
+        let now = self.clock.now();
+        match runtime.block_on(self.plan.upcoming(now, PENDING_ANNOUNCEMENTS)) {
+            Ok(pending) => self.pending = pending,
+            Err(e) => self.status = format!("Failed to fetch schedule: {e}"),
+        }
+        self.pending_refreshed = Some(now);
+    }
+
+    fn record(&mut self, activity: Activity) {
+        let time = self.clock.now().with_timezone(&Local).format("%H:%M:%S");
+
+        let line = match activity {
+            Activity::Sent { news } => {
+                self.consecutive_failures = 0;
+                format!(
+                    "{time} sent   {}/{} {}",
+                    news.rubric,
+                    news.number.unwrap_or(1),
+                    news.text
+                )
+            }
+            Activity::Failed { news, error } => {
+                self.consecutive_failures += 1;
+                let line = format!(
+                    "{time} FAILED {}/{} {} ({error})",
+                    news.rubric,
+                    news.number.unwrap_or(1),
+                    news.text
+                );
+                self.last_error = Some(error);
+                line
+            }
+            Activity::Scheduled { .. } | Activity::Formatted { .. } => return,
+        };
+
+        self.recent.push_front(line);
+        self.recent.truncate(RECENT_SENDS);
+    }
+
+    /// Sends the news typed at the prompt, in the form "<slot> <text>".
+    fn submit(&mut self) {
+        let parsed = self
+            .input
+            .split_once(' ')
+            .and_then(|(number, text)| Some((number.parse::<i8>().ok()?, text.trim())))
+            .filter(|(number, text)| (1..=10).contains(number) && !text.is_empty());
+
+        match parsed {
+            Some((number, text)) => {
+                let command = MonitorCommand::SendNews {
+                    number,
+                    text: text.to_string(),
+                };
+                self.status = match self.commands.send(command) {
+                    Ok(()) => format!("Sending news to slot {number}"),
+                    Err(_) => "Announcer has stopped".to_string(),
+                };
+                self.input.clear();
+            }
+            None => {
+                self.status = "Enter \"<slot> <text>\", where slot is 1 to 10".to_string();
+            }
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [pending_area, recent_area, health_area, input_area] = Layout::vertical([
+            Constraint::Percentage(45),
+            Constraint::Fill(1),
+            Constraint::Length(3),
+            Constraint::Length(3),
+        ])
+        .areas(frame.area());
+
+        let pending = self.pending.iter().map(|announcement| {
+            format!(
+                "{} {:<8} <{}> {}",
+                announcement.time.with_timezone(&Local).format("%a %H:%M"),
+                announcement.kind,
+                announcement.venue,
+                announcement.title
+            )
+        });
+        frame.render_widget(
+            List::new(pending).block(Block::bordered().title("Pending announcements")),
+            pending_area,
+        );
+
+        frame.render_widget(
+            List::new(self.recent.iter().map(String::as_str))
+                .block(Block::bordered().title("Recent sends")),
+            recent_area,
+        );
+
+        let health = match (&self.last_error, self.consecutive_failures) {
+            (_, 0) => "DAPNET: ok".to_string(),
+            (Some(error), n) => format!("DAPNET: {n} failures in a row, last: {error}"),
+            (None, n) => format!("DAPNET: {n} failures in a row"),
+        };
+        frame.render_widget(
+            Paragraph::new(format!("{health} | {}", self.status))
+                .block(Block::bordered().title("Status")),
+            health_area,
+        );
+
+        frame.render_widget(
+            Paragraph::new(format!("> {}", self.input)).block(Block::bordered().title("Send news")),
+            input_area,
+        );
+    }
+}
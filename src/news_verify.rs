@@ -0,0 +1,38 @@
+use crate::{dapnet::Dapnet, event_news::RubricNews};
+use tracing::{info, warn};
+
+/// Reads back the rubric a news item was posted to and checks that the expected text is present
+/// in the expected slot.
+pub(crate) async fn news_is_present(dapnet: &Dapnet, news: &RubricNews) -> bool {
+    let rubric_news = match dapnet.get_news(&news.rubric).await {
+        Ok(Some(rubric_news)) => rubric_news,
+        Ok(None) => {
+            warn!("Rubric {} has no news", news.rubric);
+            return false;
+        }
+        Err(e) => {
+            warn!("Failed to read back news for rubric {}: {e}", news.rubric);
+            return false;
+        }
+    };
+
+    let slot = news.number.unwrap_or(1);
+
+    match rubric_news.iter().find(|n| n.number.unwrap_or(1) == slot) {
+        Some(n) if n.text == news.text => {
+            info!("News verified in slot {slot}");
+            true
+        }
+        Some(n) => {
+            warn!(
+                "News in slot {slot} does not match what was sent, found: \"{}\" (sent by {})",
+                n.text, n.sender
+            );
+            false
+        }
+        None => {
+            warn!("No news found in slot {slot}");
+            false
+        }
+    }
+}
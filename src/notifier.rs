@@ -0,0 +1,207 @@
+use crate::{
+    event_news::{normalise_venue_name, AnnouncementKind, RubricNews},
+    tiers::Delivery,
+    AnnouncementState, Context, SendOutcome,
+};
+use chrono::{DateTime, Utc};
+use emfcamp_schedule_api::schedule::event::Event;
+use futures_util::future::LocalBoxFuture;
+use metrics::counter;
+
+/// An announcement of an event, in the forms it is delivered in.
+pub(crate) struct Announcement {
+    pub(crate) news: RubricNews,
+
+    /// Text of calls announcing the event
+    pub(crate) call_text: String,
+}
+
+/// Somewhere announcements are delivered to.
+pub(crate) trait Notifier {
+    /// Name of the target, used in logs and metric labels
+    fn name(&self) -> &str;
+
+    /// Checks if an event's announcements, delivered in the given way, go to this target.
+    fn accepts(&self, event: &Event, delivery: Delivery) -> bool;
+
+    /// Checks that the announcement should be sent to this target (e.g. it is not a duplicate),
+    /// recording it as sent if so.
+    fn prepare(
+        &self,
+        ctx: &Context,
+        state: &mut AnnouncementState,
+        kind: AnnouncementKind,
+        announcement: &Announcement,
+        event_start: DateTime<Utc>,
+    ) -> bool;
+
+    /// Text of the announcement as this target is sent it.
+    fn text<'a>(&self, announcement: &'a Announcement) -> &'a str {
+        &announcement.news.text
+    }
+
+    /// Sends the announcement.
+    fn notify<'a>(
+        &'a self,
+        ctx: &'a Context,
+        announcement: &'a Announcement,
+    ) -> LocalBoxFuture<'a, SendOutcome>;
+}
+
+/// Sends announcements as news to the announcement rubric.
+pub(crate) struct RubricNotifier;
+
+impl Notifier for RubricNotifier {
+    fn name(&self) -> &str {
+        "rubric"
+    }
+
+    fn accepts(&self, _event: &Event, delivery: Delivery) -> bool {
+        delivery.includes_news()
+    }
+
+    fn prepare(
+        &self,
+        ctx: &Context,
+        state: &mut AnnouncementState,
+        kind: AnnouncementKind,
+        announcement: &Announcement,
+        event_start: DateTime<Utc>,
+    ) -> bool {
+        crate::prepare_news(ctx, state, kind, &announcement.news, event_start)
+    }
+
+    fn notify<'a>(
+        &'a self,
+        ctx: &'a Context,
+        announcement: &'a Announcement,
+    ) -> LocalBoxFuture<'a, SendOutcome> {
+        Box::pin(crate::send_news(ctx, &announcement.news))
+    }
+}
+
+/// Sends announcements as calls to a list of recipients.
+pub(crate) struct CallNotifier {
+    pub(crate) name: String,
+    pub(crate) recipients: Vec<String>,
+    pub(crate) transmitter_groups: Vec<String>,
+
+    /// Only send events in tiers delivered by call, rather than every announced event
+    pub(crate) tiered: bool,
+
+    /// Only send events at these venues (events at every venue if empty)
+    pub(crate) venues: Vec<String>,
+}
+
+impl Notifier for CallNotifier {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn accepts(&self, event: &Event, delivery: Delivery) -> bool {
+        let venue_matches = self.venues.is_empty()
+            || self
+                .venues
+                .iter()
+                .any(|venue| normalise_venue_name(venue) == normalise_venue_name(&event.venue));
+
+        let delivery_matches = if self.tiered {
+            delivery.includes_call()
+        } else {
+            delivery != Delivery::Nothing
+        };
+
+        venue_matches && delivery_matches
+    }
+
+    fn prepare(
+        &self,
+        ctx: &Context,
+        state: &mut AnnouncementState,
+        _kind: AnnouncementKind,
+        announcement: &Announcement,
+        _event_start: DateTime<Utc>,
+    ) -> bool {
+        !crate::is_duplicate(
+            ctx,
+            state,
+            &format!("call/{}", self.name),
+            &announcement.call_text,
+        )
+    }
+
+    fn text<'a>(&self, announcement: &'a Announcement) -> &'a str {
+        &announcement.call_text
+    }
+
+    fn notify<'a>(
+        &'a self,
+        ctx: &'a Context,
+        announcement: &'a Announcement,
+    ) -> LocalBoxFuture<'a, SendOutcome> {
+        Box::pin(crate::send_call(
+            ctx,
+            &announcement.call_text,
+            &self.recipients,
+            &self.transmitter_groups,
+        ))
+    }
+}
+
+/// Parses a call target in the form name=RECIPIENT+RECIPIENT.
+pub(crate) fn parse_call_target(s: &str) -> anyhow::Result<(String, Vec<String>)> {
+    let (name, recipients) = s.split_once('=').ok_or_else(|| {
+        anyhow::anyhow!("Call target must be in the form name=RECIPIENT+RECIPIENT")
+    })?;
+
+    let recipients: Vec<String> = recipients
+        .split('+')
+        .map(str::trim)
+        .filter(|r| !r.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    if recipients.is_empty() {
+        return Err(anyhow::anyhow!("Call target {name} has no recipients"));
+    }
+
+    Ok((name.trim().to_string(), recipients))
+}
+
+/// Sends an announcement to each of the notifiers, returning the best outcome of any of them
+/// (i.e. it was sent if any of them sent it).
+///
+/// Once it has been sent, however it was delivered, a copy is sent to the canary callsign (if
+/// there is one) with the text the first target to send it was sent.
+pub(crate) async fn notify_all(
+    ctx: &Context,
+    notifiers: &[&dyn Notifier],
+    announcement: &Announcement,
+) -> SendOutcome {
+    let mut best = SendOutcome::Failed;
+    let mut sent_text = None;
+
+    for notifier in notifiers {
+        let outcome = notifier.notify(ctx, announcement).await;
+        counter!(
+            "notifier_announcements",
+            "target" => notifier.name().to_string(),
+            "result" => outcome.label()
+        )
+        .increment(1);
+
+        if outcome == SendOutcome::Sent || best == SendOutcome::Failed {
+            best = outcome;
+        }
+
+        if outcome.is_announced() && sent_text.is_none() {
+            sent_text = Some(notifier.text(announcement));
+        }
+    }
+
+    if let (Some(callsign), Some(text)) = (&ctx.cli.canary_callsign, sent_text) {
+        crate::send_canary_page(ctx, callsign, text).await;
+    }
+
+    best
+}
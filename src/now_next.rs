@@ -0,0 +1,217 @@
+use crate::{
+    event_news::{NewsSettings, RubricNews},
+    message_length,
+};
+use chrono::{DateTime, Utc};
+use emfcamp_schedule_api::schedule::event::Event;
+use std::collections::BTreeMap;
+
+/// Builds "now & next" news for each rubric slot, giving the event currently running at each
+/// venue and the one after it, e.g. "<Stg A> Now: Opening | 11:00 Robots".
+///
+/// Venues that share a slot are combined into one news item. Slots with nothing running or
+/// coming up get no news.
+pub(crate) fn build_now_next(
+    events: &[Event],
+    now: DateTime<Utc>,
+    settings: &NewsSettings,
+    rubric: &str,
+) -> Vec<RubricNews> {
+    let mut venues = BTreeMap::<&str, (Option<&Event>, Option<&Event>)>::new();
+
+    for event in events {
+        let start = event.start.with_timezone(&Utc);
+        let end = event.end.with_timezone(&Utc);
+
+        let (current, next) = venues.entry(event.venue.as_str()).or_default();
+
+        if start <= now && now < end {
+            if current.is_none_or(|current| event.start > current.start) {
+                *current = Some(event);
+            }
+        } else if start > now && next.is_none_or(|next| event.start < next.start) {
+            *next = Some(event);
+        }
+    }
+
+    let mut slots = BTreeMap::<i8, Vec<String>>::new();
+
+    for (venue, (current, next)) in venues {
+        if current.is_none() && next.is_none() {
+            continue;
+        }
+
+        let Some((short_name, number)) = settings.venue_details(venue) else {
+            continue;
+        };
+
+        let mut parts = Vec::new();
+        if let Some(current) = current {
+            parts.push(format!("Now: {}", current.title));
+        }
+        if let Some(next) = next {
+            parts.push(format!("{} {}", next.start.format("%H:%M"), next.title));
+        }
+
+        slots
+            .entry(number)
+            .or_default()
+            .push(format!("<{short_name}> {}", parts.join(" | ")));
+    }
+
+    slots
+        .into_iter()
+        .map(|(number, venues)| {
+            let text = settings.charset.sanitise(venues.join("; "));
+            let text = message_length::shorten(&text, settings.max_length, &[]);
+
+            RubricNews {
+                rubric: rubric.to_string(),
+                number: Some(number),
+                text,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        clock::SystemClock,
+        pocsag::{CharsetPolicy, CharsetSettings},
+    };
+    use chrono::Duration;
+    use std::{collections::HashMap, sync::Arc};
+
+    fn event(venue: &str, start: &str, end: &str, title: &str) -> Event {
+        serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "start_date": start,
+            "end_date": end,
+            "venue": venue,
+            "title": title,
+            "speaker": "",
+            "description": "",
+            "type": "talk",
+        }))
+        .unwrap()
+    }
+
+    fn settings() -> NewsSettings {
+        NewsSettings {
+            rubric: "emfcamp".to_string(),
+            track_rubrics: HashMap::new(),
+            venue_rubrics: false,
+            slots: None,
+            venue_map: None,
+            max_length: 80,
+            description_snippet: false,
+            relative_start_time: false,
+            upcoming_template: None,
+            starting_now_template: None,
+            call_template: None,
+            time_offset: Duration::zero(),
+            clock: Arc::new(SystemClock),
+            charset: CharsetSettings {
+                policy: CharsetPolicy::Warn,
+                transliterate: false,
+                replacements: Vec::new(),
+            },
+            redacted_fields: Vec::new(),
+        }
+    }
+
+    fn news(number: i8, text: &str) -> RubricNews {
+        RubricNews {
+            rubric: "emfnow".to_string(),
+            number: Some(number),
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn current_and_next_events_are_given_for_each_slot() {
+        let events = [
+            event(
+                "Stage A",
+                "2024-05-30T09:00:00+01:00",
+                "2024-05-30T10:00:00+01:00",
+                "Breakfast",
+            ),
+            event(
+                "Stage A",
+                "2024-05-30T10:00:00+01:00",
+                "2024-05-30T11:00:00+01:00",
+                "Opening",
+            ),
+            event(
+                "Stage A",
+                "2024-05-30T12:00:00+01:00",
+                "2024-05-30T13:00:00+01:00",
+                "Lasers",
+            ),
+            event(
+                "Stage A",
+                "2024-05-30T11:00:00+01:00",
+                "2024-05-30T12:00:00+01:00",
+                "Robots",
+            ),
+            event(
+                "Stage B",
+                "2024-05-30T09:00:00+01:00",
+                "2024-05-30T10:00:00+01:00",
+                "Yoga",
+            ),
+            event(
+                "Workshop 1 (NottingHack)",
+                "2024-05-30T10:00:00+01:00",
+                "2024-05-30T12:00:00+01:00",
+                "Soldering",
+            ),
+            event(
+                "Workshop 2 (Milliways)",
+                "2024-05-30T11:00:00+01:00",
+                "2024-05-30T12:00:00+01:00",
+                "Knitting",
+            ),
+        ];
+
+        let news_items = build_now_next(
+            &events,
+            "2024-05-30T09:30:00Z".parse().unwrap(),
+            &settings(),
+            "emfnow",
+        );
+
+        assert_eq!(
+            news_items,
+            [
+                news(1, "<Stg A> Now: Opening | 11:00 Robots"),
+                news(4, "<Wksp 1> Now: Soldering; <Wksp 2> 11:00 Knitting"),
+            ]
+        );
+    }
+
+    #[test]
+    fn news_is_shortened_to_the_maximum_length() {
+        let events = [event(
+            "Stage A",
+            "2024-05-30T10:00:00+01:00",
+            "2024-05-30T11:00:00+01:00",
+            "A very long title for an event that goes on and on and on and on and on",
+        )];
+        let mut settings = settings();
+        settings.max_length = 30;
+
+        let news_items = build_now_next(
+            &events,
+            "2024-05-30T09:30:00Z".parse().unwrap(),
+            &settings,
+            "emfnow",
+        );
+
+        assert_eq!(news_items.len(), 1);
+        assert!(news_items[0].text.chars().count() <= 30);
+    }
+}
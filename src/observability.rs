@@ -0,0 +1,453 @@
+use crate::{
+    activity::{Activity, ActivityFeed},
+    clock::Clock,
+    health::Health,
+    plan::AnnouncementPlan,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use futures_util::{stream, SinkExt};
+use http_body_util::{combinators::BoxBody, BodyExt, Empty, Full, StreamBody};
+use hyper::{
+    body::{Bytes, Frame, Incoming},
+    header::{self, HeaderMap},
+    server::conn::http1,
+    service::service_fn,
+    Method, Request, Response, StatusCode,
+};
+use hyper_util::rt::TokioIo;
+use metrics_exporter_prometheus::PrometheusHandle;
+use std::{
+    convert::Infallible, fs::File, io::BufReader, net::SocketAddr, os::unix::fs::FileTypeExt,
+    path::Path, sync::Arc,
+};
+use subtle::ConstantTimeEq;
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::{TcpListener, UnixListener},
+    sync::broadcast::error::RecvError,
+};
+use tokio_rustls::{
+    rustls::{self, ServerConfig},
+    TlsAcceptor,
+};
+use tokio_tungstenite::{
+    tungstenite::{handshake::derive_accept_key, protocol::Role, Message},
+    WebSocketStream,
+};
+use tracing::{info, warn};
+
+type Body = BoxBody<Bytes, Infallible>;
+
+/// Credentials clients must present to access the observability endpoints.
+#[derive(Debug, Clone)]
+pub(crate) enum Authentication {
+    Bearer(String),
+    Basic { username: String, password: String },
+}
+
+impl Authentication {
+    fn authorization_header(&self) -> String {
+        match self {
+            Self::Bearer(token) => format!("Bearer {token}"),
+            Self::Basic { username, password } => {
+                format!("Basic {}", BASE64.encode(format!("{username}:{password}")))
+            }
+        }
+    }
+}
+
+/// Which of the observability endpoints a listener serves, so that e.g. metrics can be exposed
+/// more widely than the live activity. The health probes are served by every listener.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Endpoints {
+    All,
+
+    /// Only /metrics
+    Metrics,
+
+    /// Only /activity, /announcements and /plan.ics
+    Status,
+}
+
+impl Endpoints {
+    fn serves(self, path: &str) -> bool {
+        match self {
+            Self::All => true,
+            Self::Metrics => path == "/metrics",
+            Self::Status => matches!(path, "/activity" | "/announcements" | "/plan.ics"),
+        }
+    }
+}
+
+/// Loads a PEM certificate chain and private key for serving HTTPS.
+pub(crate) fn tls_acceptor(cert: &Path, key: &Path) -> anyhow::Result<TlsAcceptor> {
+    let certs = rustls_pemfile::certs(&mut BufReader::new(File::open(cert)?))
+        .collect::<Result<Vec<_>, _>>()?;
+    let key = rustls_pemfile::private_key(&mut BufReader::new(File::open(key)?))?
+        .ok_or_else(|| anyhow::anyhow!("No private key found in {}", key.display()))?;
+
+    let config =
+        ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_no_client_auth()
+            .with_single_cert(certs, key)?;
+
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+/// Serves metrics and live activity over HTTP.
+pub(crate) struct Observability {
+    metrics: PrometheusHandle,
+    activity: ActivityFeed,
+    plan: Arc<AnnouncementPlan>,
+    clock: Arc<dyn Clock>,
+    health: Arc<Health>,
+    authorization: Option<String>,
+    tls: Option<TlsAcceptor>,
+}
+
+impl Observability {
+    pub(crate) fn new(
+        metrics: PrometheusHandle,
+        activity: ActivityFeed,
+        plan: Arc<AnnouncementPlan>,
+        clock: Arc<dyn Clock>,
+        health: Arc<Health>,
+        authentication: Option<Authentication>,
+        tls: Option<TlsAcceptor>,
+    ) -> Self {
+        Self {
+            metrics,
+            activity,
+            plan,
+            clock,
+            health,
+            authorization: authentication.map(|a| a.authorization_header()),
+            tls,
+        }
+    }
+
+    async fn respond(&self, request: Request<Incoming>, endpoints: Endpoints) -> Response<Body> {
+        // Probes are not authenticated, so that they can be used without credentials
+        match request.uri().path() {
+            "/healthz" => return health_response(self.health.liveness().map_err(|e| vec![e])),
+            "/readyz" => return health_response(self.health.readiness()),
+            _ => {}
+        }
+
+        if let Some(expected) = &self.authorization {
+            let provided = request
+                .headers()
+                .get(header::AUTHORIZATION)
+                .map_or(&[][..], |v| v.as_bytes());
+
+            // Compared in constant time so that how long a rejection takes does not reveal how
+            // much of the credentials were right
+            if !bool::from(provided.ct_eq(expected.as_bytes())) {
+                return Response::builder()
+                    .status(StatusCode::UNAUTHORIZED)
+                    .header(header::WWW_AUTHENTICATE, "Basic, Bearer")
+                    .body(Empty::new().boxed())
+                    .unwrap();
+            }
+        }
+
+        let path = request.uri().path();
+        if !endpoints.serves(path) {
+            return Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(Empty::new().boxed())
+                .unwrap();
+        }
+
+        match path {
+            "/metrics" => Response::builder()
+                .header(header::CONTENT_TYPE, "text/plain; version=0.0.4")
+                .body(Full::new(Bytes::from(self.metrics.render())).boxed())
+                .unwrap(),
+            "/activity" => self.stream_activity(request),
+            "/announcements" => self.stream_announcements(),
+            "/plan.ics" => self.announcement_plan().await,
+            _ => Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(Empty::new().boxed())
+                .unwrap(),
+        }
+    }
+
+    /// Upgrades the connection to a WebSocket and sends each pipeline activity to it as JSON.
+    fn stream_activity(&self, mut request: Request<Incoming>) -> Response<Body> {
+        let key = match websocket_key(request.headers()) {
+            Some(key) if request.method() == Method::GET => key,
+            _ => {
+                return Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .header(header::SEC_WEBSOCKET_VERSION, WEBSOCKET_VERSION)
+                    .body(Empty::new().boxed())
+                    .unwrap();
+            }
+        };
+        let accept = derive_accept_key(key);
+
+        let mut activity = self.activity.subscribe();
+
+        tokio::spawn(async move {
+            let upgraded = match hyper::upgrade::on(&mut request).await {
+                Ok(upgraded) => upgraded,
+                Err(e) => {
+                    warn!("Failed to upgrade activity connection: {e}");
+                    return;
+                }
+            };
+            let mut websocket =
+                WebSocketStream::from_raw_socket(TokioIo::new(upgraded), Role::Server, None).await;
+
+            loop {
+                match activity.recv().await {
+                    Ok(activity) => {
+                        let message = Message::Text(activity.to_json().to_string());
+                        if let Err(e) = websocket.send(message).await {
+                            info!("Activity WebSocket closed: {e}");
+                            break;
+                        }
+                    }
+                    Err(RecvError::Lagged(n)) => {
+                        warn!("Activity WebSocket client fell behind, {n} activities not sent");
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+
+        Response::builder()
+            .status(StatusCode::SWITCHING_PROTOCOLS)
+            .header(header::CONNECTION, "upgrade")
+            .header(header::UPGRADE, "websocket")
+            .header(header::SEC_WEBSOCKET_ACCEPT, accept)
+            .body(Empty::new().boxed())
+            .unwrap()
+    }
+
+    /// Sends each announcement as it is sent to DAPNET as a server-sent event.
+    fn stream_announcements(&self) -> Response<Body> {
+        let announcements = stream::unfold(self.activity.subscribe(), |mut activity| async move {
+            loop {
+                match activity.recv().await {
+                    Ok(sent @ Activity::Sent { .. }) => {
+                        let event = format!("event: announcement\ndata: {}\n\n", sent.to_json());
+                        return Some((
+                            Ok::<_, Infallible>(Frame::data(Bytes::from(event))),
+                            activity,
+                        ));
+                    }
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(n)) => {
+                        warn!("Announcement stream client fell behind, {n} activities not sent");
+                    }
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        });
+
+        Response::builder()
+            .header(header::CONTENT_TYPE, "text/event-stream")
+            .header(header::CACHE_CONTROL, "no-cache")
+            .body(StreamBody::new(announcements).boxed())
+            .unwrap()
+    }
+
+    async fn announcement_plan(&self) -> Response<Body> {
+        match self.plan.to_ics(self.clock.as_ref()).await {
+            Ok(ics) => Response::builder()
+                .header(header::CONTENT_TYPE, "text/calendar; charset=utf-8")
+                .body(Full::new(Bytes::from(ics)).boxed())
+                .unwrap(),
+            Err(e) => {
+                warn!("Failed to build announcement plan: {e}");
+                Response::builder()
+                    .status(StatusCode::BAD_GATEWAY)
+                    .body(Empty::new().boxed())
+                    .unwrap()
+            }
+        }
+    }
+
+    /// Binds to `address` and serves requests for the given endpoints in the background, over
+    /// HTTPS if a certificate was provided.
+    pub(crate) async fn serve_tcp(
+        self: Arc<Self>,
+        address: SocketAddr,
+        endpoints: Endpoints,
+    ) -> anyhow::Result<()> {
+        let listener = TcpListener::bind(address).await?;
+        info!("Observability server listening on {address} ({endpoints:?} endpoints)");
+
+        tokio::spawn(async move {
+            loop {
+                let stream = match listener.accept().await {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        warn!("Failed to accept observability connection: {e}");
+                        continue;
+                    }
+                };
+
+                match &self.tls {
+                    Some(tls) => {
+                        let tls = tls.clone();
+                        let observability = self.clone();
+                        tokio::spawn(async move {
+                            match tls.accept(stream).await {
+                                Ok(stream) => observability.serve_connection(stream, endpoints),
+                                Err(e) => warn!("TLS handshake failed: {e}"),
+                            }
+                        });
+                    }
+                    None => self.clone().serve_connection(stream, endpoints),
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Binds to a Unix domain socket at `path` and serves requests in the background.
+    ///
+    /// A socket left behind by a previous run is replaced.
+    pub(crate) async fn serve_unix(self: Arc<Self>, path: &Path) -> anyhow::Result<()> {
+        if std::fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_socket()) {
+            std::fs::remove_file(path)?;
+        }
+
+        let listener = UnixListener::bind(path)?;
+        info!("Observability server listening on {}", path.display());
+
+        tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => self.clone().serve_connection(stream, Endpoints::All),
+                    Err(e) => warn!("Failed to accept observability connection: {e}"),
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn serve_connection<S>(self: Arc<Self>, stream: S, endpoints: Endpoints)
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        tokio::spawn(async move {
+            let service = service_fn(|request| {
+                let observability = self.clone();
+                async move { Ok::<_, Infallible>(observability.respond(request, endpoints).await) }
+            });
+
+            if let Err(e) = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .with_upgrades()
+                .await
+            {
+                warn!("Observability connection failed: {e}");
+            }
+        });
+    }
+}
+
+/// The only version of the WebSocket protocol there is (RFC 6455).
+const WEBSOCKET_VERSION: &str = "13";
+
+/// Gets the key from a request to upgrade to a WebSocket, or `None` if the request is not a valid
+/// WebSocket upgrade.
+fn websocket_key(headers: &HeaderMap) -> Option<&[u8]> {
+    let has_token = |name, token: &str| {
+        headers.get_all(name).iter().any(|value| {
+            value.to_str().is_ok_and(|value| {
+                value
+                    .split(',')
+                    .any(|t| t.trim().eq_ignore_ascii_case(token))
+            })
+        })
+    };
+
+    if !has_token(header::CONNECTION, "upgrade") || !has_token(header::UPGRADE, "websocket") {
+        return None;
+    }
+
+    if headers.get(header::SEC_WEBSOCKET_VERSION)? != WEBSOCKET_VERSION {
+        return None;
+    }
+
+    headers
+        .get(header::SEC_WEBSOCKET_KEY)
+        .map(|key| key.as_bytes())
+}
+
+/// Responds to a health probe with "ok", or the problems found and a 503 status.
+fn health_response(result: Result<(), Vec<String>>) -> Response<Body> {
+    let (status, body) = match result {
+        Ok(()) => (StatusCode::OK, "ok\n".to_string()),
+        Err(problems) => (StatusCode::SERVICE_UNAVAILABLE, problems.join("\n") + "\n"),
+    };
+
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "text/plain")
+        .body(Full::new(Bytes::from(body)).boxed())
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::header::HeaderValue;
+
+    fn headers(headers: &[(header::HeaderName, &'static str)]) -> HeaderMap {
+        headers
+            .iter()
+            .map(|(name, value)| (name.clone(), HeaderValue::from_static(value)))
+            .collect()
+    }
+
+    fn upgrade_headers() -> HeaderMap {
+        headers(&[
+            (header::CONNECTION, "keep-alive, Upgrade"),
+            (header::UPGRADE, "websocket"),
+            (header::SEC_WEBSOCKET_VERSION, "13"),
+            (header::SEC_WEBSOCKET_KEY, "dGhlIHNhbXBsZSBub25jZQ=="),
+        ])
+    }
+
+    #[test]
+    fn websocket_upgrades_are_accepted() {
+        assert_eq!(
+            websocket_key(&upgrade_headers()),
+            Some(b"dGhlIHNhbXBsZSBub25jZQ==".as_slice())
+        );
+    }
+
+    #[test]
+    fn invalid_websocket_upgrades_are_rejected() {
+        for (name, value) in [
+            (header::UPGRADE, "h2c"),
+            (header::CONNECTION, "keep-alive"),
+            (header::SEC_WEBSOCKET_VERSION, "8"),
+        ] {
+            let mut headers = upgrade_headers();
+            headers.insert(name, HeaderValue::from_static(value));
+            assert_eq!(websocket_key(&headers), None, "{value}");
+        }
+
+        for name in [
+            header::UPGRADE,
+            header::CONNECTION,
+            header::SEC_WEBSOCKET_VERSION,
+            header::SEC_WEBSOCKET_KEY,
+        ] {
+            let mut headers = upgrade_headers();
+            headers.remove(&name);
+            assert_eq!(websocket_key(&headers), None, "{name}");
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use chrono::{DateTime, Duration, Utc};
+
+/// Tracks how long schedule fetches have been failing for.
+pub(crate) struct OutageTracker {
+    threshold: Duration,
+    started: Option<DateTime<Utc>>,
+    alerted: bool,
+}
+
+impl OutageTracker {
+    pub(crate) fn new(threshold: Duration) -> Self {
+        Self {
+            threshold,
+            started: None,
+            alerted: false,
+        }
+    }
+
+    /// Records a failure, returning the length of the outage if it has just exceeded the alert
+    /// threshold (this happens at most once per outage).
+    pub(crate) fn record_failure(&mut self, now: DateTime<Utc>) -> Option<Duration> {
+        let started = *self.started.get_or_insert(now);
+        let duration = now - started;
+
+        if !self.alerted && duration >= self.threshold {
+            self.alerted = true;
+            Some(duration)
+        } else {
+            None
+        }
+    }
+
+    /// Records a success, returning the length of the outage that just ended (if there was one).
+    pub(crate) fn record_success(&mut self, now: DateTime<Utc>) -> Option<Duration> {
+        self.alerted = false;
+        self.started.take().map(|started| now - started)
+    }
+}
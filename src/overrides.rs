@@ -0,0 +1,138 @@
+use chrono::{DateTime, FixedOffset};
+use emfcamp_schedule_api::schedule::event::Event;
+use serde::Deserialize;
+use std::{collections::HashMap, path::Path};
+
+/// Local corrections to a single event in the schedule.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct EventOverride {
+    pub(crate) title: Option<String>,
+    pub(crate) venue: Option<String>,
+    pub(crate) start: Option<DateTime<FixedOffset>>,
+    pub(crate) end: Option<DateTime<FixedOffset>>,
+
+    /// Do not announce the event at all
+    pub(crate) hidden: bool,
+}
+
+/// Local corrections to the schedule keyed by event ID, loaded from a JSON file like
+/// `{"123": {"title": "Corrected title", "start": "2024-05-30T14:00:00+01:00"}, "456": {"hidden": true}}`.
+#[derive(Debug, Default)]
+pub(crate) struct ScheduleOverrides {
+    events: HashMap<u32, EventOverride>,
+}
+
+impl ScheduleOverrides {
+    pub(crate) fn load(path: &Path) -> anyhow::Result<Self> {
+        let file = std::fs::File::open(path).map_err(|e| {
+            anyhow::anyhow!("Failed to open overrides file {}: {e}", path.display())
+        })?;
+
+        Ok(Self {
+            events: serde_json::from_reader(file)?,
+        })
+    }
+
+    /// Applies any overrides for the event, returning false if the event is hidden.
+    pub(crate) fn apply(&self, event: &mut Event) -> bool {
+        let Some(event_override) = self.events.get(&event.id) else {
+            return true;
+        };
+
+        if let Some(title) = &event_override.title {
+            event.title = title.clone();
+        }
+        if let Some(venue) = &event_override.venue {
+            event.venue = venue.clone();
+        }
+        if let Some(start) = event_override.start {
+            event.start = start;
+        }
+        if let Some(end) = event_override.end {
+            event.end = end;
+        }
+
+        !event_override.hidden
+    }
+
+    /// Checks if the start of an event is corrected.
+    pub(crate) fn moves(&self, id: u32) -> bool {
+        self.events
+            .get(&id)
+            .is_some_and(|event_override| event_override.start.is_some())
+    }
+
+    /// Checks if the start of any event is corrected.
+    pub(crate) fn moves_any(&self) -> bool {
+        self.events
+            .values()
+            .any(|event_override| event_override.start.is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(id: u32) -> Event {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "start_date": "2024-05-30T14:00:00+01:00",
+            "end_date": "2024-05-30T15:00:00+01:00",
+            "venue": "Stage A",
+            "title": "Opening ceremony",
+            "speaker": "",
+            "description": "",
+            "type": "talk",
+        }))
+        .unwrap()
+    }
+
+    fn overrides(json: &str) -> ScheduleOverrides {
+        ScheduleOverrides {
+            events: serde_json::from_str(json).unwrap(),
+        }
+    }
+
+    #[test]
+    fn overrides_correct_events() {
+        let overrides = overrides(
+            r#"{"1": {"title": "Opening", "venue": "Stage B", "start": "2024-05-30T14:30:00+01:00"}}"#,
+        );
+
+        let mut corrected = event(1);
+        assert!(overrides.apply(&mut corrected));
+        assert_eq!(corrected.title, "Opening");
+        assert_eq!(corrected.venue, "Stage B");
+        assert_eq!(
+            corrected.start,
+            DateTime::parse_from_rfc3339("2024-05-30T14:30:00+01:00").unwrap()
+        );
+        assert_eq!(corrected.end, event(1).end);
+
+        let mut untouched = event(2);
+        assert!(overrides.apply(&mut untouched));
+        assert_eq!(untouched, event(2));
+
+        assert!(overrides.moves(1));
+        assert!(!overrides.moves(2));
+        assert!(overrides.moves_any());
+    }
+
+    #[test]
+    fn hidden_events_are_left_out() {
+        let overrides = overrides(r#"{"1": {"hidden": true}}"#);
+
+        assert!(!overrides.apply(&mut event(1)));
+        assert!(!overrides.moves_any());
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        assert!(serde_json::from_str::<HashMap<u32, EventOverride>>(
+            r#"{"1": {"titel": "Opening"}}"#
+        )
+        .is_err());
+    }
+}
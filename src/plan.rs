@@ -0,0 +1,360 @@
+use crate::{
+    clock::Clock,
+    event_news::{AnnouncementKind, EventExt, NewsSettings},
+    filter::EventFilter,
+    schedule_source::ScheduleSource,
+    suppress,
+    tiers::{Delivery, TierSettings},
+};
+use chrono::{DateTime, Duration, Utc};
+use emfcamp_schedule_api::schedule::event::Event;
+use std::{collections::HashSet, path::PathBuf};
+use tracing::error;
+
+/// Works out when events will be announced.
+pub(crate) struct AnnouncementPlan {
+    pub(crate) schedule: ScheduleSource,
+    pub(crate) event_start_offset: Duration,
+
+    /// Offset of the schedule's clock from the real one
+    pub(crate) time_offset: Duration,
+    pub(crate) filter: EventFilter,
+
+    /// Filter for starting now announcements, if they are enabled
+    pub(crate) starting_now_filter: Option<EventFilter>,
+
+    pub(crate) tiers: TierSettings,
+
+    /// Events that must never be announced, re-read each time it is needed
+    pub(crate) suppressed_events_file: Option<PathBuf>,
+}
+
+/// An announcement that will be made.
+pub(crate) struct PlannedAnnouncement {
+    pub(crate) time: DateTime<Utc>,
+    pub(crate) kind: &'static str,
+    pub(crate) title: String,
+    pub(crate) venue: String,
+}
+
+impl AnnouncementPlan {
+    /// Gets when an announcement of the given kind is due for an event.
+    pub(crate) fn announcement_time(&self, event: &Event, kind: AnnouncementKind) -> DateTime<Utc> {
+        let start = event.start.with_timezone(&Utc) - self.time_offset;
+
+        match kind {
+            AnnouncementKind::Upcoming => start + self.event_start_offset,
+            AnnouncementKind::StartingNow => start,
+        }
+    }
+
+    /// Returns why an announcement of the given kind should not be made for an event, or `None`
+    /// if it should.
+    ///
+    /// This decides what is announced, both when announcing and when planning, so that the plan
+    /// always matches what is announced.
+    pub(crate) fn rejection_reason(
+        &self,
+        event: &Event,
+        kind: AnnouncementKind,
+    ) -> Option<&'static str> {
+        self.rejection_reason_with(event, kind, self.suppressed_events().as_ref())
+    }
+
+    /// Keeps the events that may be announced as upcoming.
+    pub(crate) fn announceable(&self, events: Vec<Event>) -> Vec<Event> {
+        let suppressed = self.suppressed_events();
+
+        events
+            .into_iter()
+            .filter(|event| {
+                self.rejection_reason_with(event, AnnouncementKind::Upcoming, suppressed.as_ref())
+                    .is_none()
+            })
+            .collect()
+    }
+
+    /// Loads the suppress list, giving `None` if it cannot be read, in which case nothing is
+    /// announced (erring on the side of not announcing suppressed events).
+    fn suppressed_events(&self) -> Option<HashSet<u32>> {
+        let Some(path) = &self.suppressed_events_file else {
+            return Some(HashSet::new());
+        };
+
+        suppress::load_suppressed_events(path)
+            .map_err(|e| error!("Failed to load suppress list, not announcing events: {e}"))
+            .ok()
+    }
+
+    fn rejection_reason_with(
+        &self,
+        event: &Event,
+        kind: AnnouncementKind,
+        suppressed: Option<&HashSet<u32>>,
+    ) -> Option<&'static str> {
+        if suppressed.is_none_or(|suppressed| suppressed.contains(&event.id)) {
+            return Some("is on the suppress list");
+        }
+
+        let filter = match kind {
+            AnnouncementKind::Upcoming => Some(&self.filter),
+            AnnouncementKind::StartingNow => self.starting_now_filter.as_ref(),
+        };
+        let Some(filter) = filter else {
+            return Some("is not announced as starting now announcements are disabled");
+        };
+        if let Some(reason) = filter.rejection_reason(event) {
+            return Some(reason);
+        }
+
+        if self.tiers.delivery_for(event) == Delivery::Nothing {
+            return Some("is in a tier that is not announced");
+        }
+
+        None
+    }
+
+    /// Lists each announcement that will be made for the events, as the event, the kind of
+    /// announcement and when it will be made.
+    fn announcements<'a>(
+        &self,
+        events: &'a [Event],
+    ) -> Vec<(&'a Event, AnnouncementKind, DateTime<Utc>)> {
+        let suppressed = self.suppressed_events();
+
+        let mut announcements = Vec::new();
+
+        for event in events {
+            for kind in [AnnouncementKind::Upcoming, AnnouncementKind::StartingNow] {
+                if self
+                    .rejection_reason_with(event, kind, suppressed.as_ref())
+                    .is_none()
+                {
+                    announcements.push((event, kind, self.announcement_time(event, kind)));
+                }
+            }
+        }
+
+        announcements
+    }
+
+    /// Gets the time of the first announcement due after `after`.
+    pub(crate) async fn next_announcement_after(
+        &self,
+        after: DateTime<Utc>,
+    ) -> anyhow::Result<Option<DateTime<Utc>>> {
+        let events = self.schedule.events().await?;
+
+        Ok(self
+            .announcements(&events)
+            .into_iter()
+            .map(|(_, _, time)| time)
+            .filter(|time| *time > after)
+            .min())
+    }
+
+    /// Lists the first `limit` announcements due after `after`, in the order they will be made.
+    pub(crate) async fn upcoming(
+        &self,
+        after: DateTime<Utc>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<PlannedAnnouncement>> {
+        let events = self.schedule.events().await?;
+
+        let mut announcements = self.announcements(&events);
+        announcements.retain(|(_, _, time)| *time > after);
+        announcements.sort_by_key(|(_, _, time)| *time);
+
+        Ok(announcements
+            .into_iter()
+            .take(limit)
+            .map(|(event, kind, time)| PlannedAnnouncement {
+                time,
+                kind: kind_name(kind),
+                title: event.title.clone(),
+                venue: event.venue.clone(),
+            })
+            .collect())
+    }
+
+    /// Prints a table of every announcement that will be made, with the news that would be sent.
+    pub(crate) async fn print(&self, news_settings: &NewsSettings) -> anyhow::Result<()> {
+        let events = self.schedule.events().await?;
+
+        let mut announcements = self.announcements(&events);
+        announcements.sort_by_key(|(_, _, time)| *time);
+
+        println!(
+            "{:<17} {:<8} {:<12} {:>3} Text",
+            "Time (UTC)", "Kind", "Target", "Len"
+        );
+
+        for (event, kind, time) in &announcements {
+            let time = time.format("%Y-%m-%d %H:%M");
+            let kind_name = kind_name(*kind);
+
+            match event.to_rubric_news(news_settings, *kind) {
+                Some(news) => println!(
+                    "{time:<17} {kind_name:<8} {:<12} {:>3} {}",
+                    format!("{}/{}", news.rubric, news.number.unwrap_or(1)),
+                    news.text.chars().count(),
+                    news.text
+                ),
+                None => println!(
+                    "{time:<17} {kind_name:<8} {:<12} {:>3} (not announced) {}",
+                    "-", "-", event.title
+                ),
+            }
+        }
+
+        println!("{} announcements", announcements.len());
+
+        Ok(())
+    }
+
+    /// Builds an iCalendar feed with an entry for each announcement that will be made.
+    pub(crate) async fn to_ics(&self, clock: &dyn Clock) -> anyhow::Result<String> {
+        let events = self.schedule.events().await?;
+        let now = clock.now();
+
+        let mut ics = String::new();
+        ics.push_str("BEGIN:VCALENDAR\r\n");
+        ics.push_str("VERSION:2.0\r\n");
+        ics.push_str("PRODID:-//emfcamp-dapnet-schedule-announcer//announcement plan//EN\r\n");
+
+        for (event, kind, time) in self.announcements(&events) {
+            push_ics_event(&mut ics, event, kind, time, now);
+        }
+
+        ics.push_str("END:VCALENDAR\r\n");
+
+        Ok(ics)
+    }
+}
+
+fn push_ics_event(
+    ics: &mut String,
+    event: &Event,
+    kind: AnnouncementKind,
+    time: DateTime<Utc>,
+    now: DateTime<Utc>,
+) {
+    const FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+    let kind = kind_name(kind);
+
+    ics.push_str("BEGIN:VEVENT\r\n");
+    ics.push_str(&format!(
+        "UID:{}-{kind}@emfcamp-dapnet-schedule-announcer\r\n",
+        event.id
+    ));
+    ics.push_str(&format!("DTSTAMP:{}\r\n", now.format(FORMAT)));
+    ics.push_str(&format!("DTSTART:{}\r\n", time.format(FORMAT)));
+    ics.push_str("DURATION:PT1M\r\n");
+    ics.push_str(&format!(
+        "SUMMARY:Announce ({kind}): {}\r\n",
+        escape_text(&event.title)
+    ));
+    ics.push_str(&format!("LOCATION:{}\r\n", escape_text(&event.venue)));
+    ics.push_str("END:VEVENT\r\n");
+}
+
+fn kind_name(kind: AnnouncementKind) -> &'static str {
+    match kind {
+        AnnouncementKind::Upcoming => "upcoming",
+        AnnouncementKind::StartingNow => "now",
+    }
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event() -> Event {
+        serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "start_date": "2024-05-30T14:00:00+01:00",
+            "end_date": "2024-05-30T15:00:00+01:00",
+            "venue": "Stage A",
+            "title": "Opening ceremony",
+            "speaker": "",
+            "description": "",
+            "type": "talk",
+        }))
+        .unwrap()
+    }
+
+    fn plan(time_offset: Duration, starting_now: bool) -> AnnouncementPlan {
+        let filter = EventFilter {
+            min_duration: None,
+            max_duration: None,
+            enabled_weekdays: Vec::new(),
+            enabled_dates: Vec::new(),
+            include_venues: Vec::new(),
+            exclude_venues: Vec::new(),
+            include_types: Vec::new(),
+            exclude_title: None,
+        };
+
+        AnnouncementPlan {
+            schedule: ScheduleSource::new("http://localhost/".parse().unwrap(), None),
+            event_start_offset: -Duration::minutes(10),
+            time_offset,
+            filter: filter.clone(),
+            starting_now_filter: starting_now.then_some(filter),
+            tiers: TierSettings {
+                rules: Vec::new(),
+                headline: Delivery::Both,
+                normal: Delivery::News,
+                low: Delivery::Nothing,
+            },
+            suppressed_events_file: None,
+        }
+    }
+
+    #[test]
+    fn announcements_are_timed_from_the_event_start() {
+        let plan = plan(Duration::zero(), true);
+
+        assert_eq!(
+            plan.announcement_time(&event(), AnnouncementKind::Upcoming),
+            "2024-05-30T12:50:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        assert_eq!(
+            plan.announcement_time(&event(), AnnouncementKind::StartingNow),
+            "2024-05-30T13:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+    }
+
+    #[test]
+    fn announcement_times_are_moved_by_the_time_offset() {
+        // The schedule's clock is two days ahead of the real one
+        let plan = plan(Duration::days(2), true);
+
+        assert_eq!(
+            plan.announcement_time(&event(), AnnouncementKind::Upcoming),
+            "2024-05-28T12:50:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+    }
+
+    #[test]
+    fn starting_now_announcements_can_be_disabled() {
+        let plan = plan(Duration::zero(), false);
+
+        assert_eq!(
+            plan.rejection_reason(&event(), AnnouncementKind::Upcoming),
+            None
+        );
+        assert_eq!(
+            plan.rejection_reason(&event(), AnnouncementKind::StartingNow),
+            Some("is not announced as starting now announcements are disabled")
+        );
+    }
+}
@@ -0,0 +1,92 @@
+use clap::ValueEnum;
+use metrics::counter;
+use tracing::warn;
+
+/// What happens to characters that cannot be sent as POCSAG alphanumeric text.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub(crate) enum CharsetPolicy {
+    /// Replace them with "?"
+    Replace,
+    /// Remove them
+    Drop,
+    /// Send them anyway, logging a warning
+    Warn,
+}
+
+/// How text is made safe to send via POCSAG.
+#[derive(Debug, Clone)]
+pub(crate) struct CharsetSettings {
+    /// What to do with characters that are still not POCSAG safe after replacement and
+    /// transliteration
+    pub(crate) policy: CharsetPolicy,
+
+    /// Replace characters that are not POCSAG safe with their closest ASCII equivalent (e.g. "é"
+    /// with "e" and curly quotes with straight ones)
+    pub(crate) transliterate: bool,
+
+    /// Text to replace before transliterating, as (from, to) pairs
+    pub(crate) replacements: Vec<(String, String)>,
+}
+
+impl CharsetSettings {
+    /// Makes text POCSAG safe, applying the replacements then transliterating (if enabled) before
+    /// applying the policy to any characters that remain unsafe.
+    pub(crate) fn sanitise(&self, text: String) -> String {
+        let mut text = self
+            .replacements
+            .iter()
+            .fold(text, |text, (from, to)| text.replace(from, to));
+
+        if self.transliterate && !text.chars().all(is_pocsag_safe) {
+            counter!("pocsag_transliterated_messages").increment(1);
+            text = transliterate(&text);
+        }
+
+        sanitise(text, self.policy)
+    }
+}
+
+/// Replaces characters that are not POCSAG safe with an ASCII equivalent, leaving those that have
+/// none (or whose equivalent is still unsafe) as they are.
+fn transliterate(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            if is_pocsag_safe(c) {
+                return c.to_string();
+            }
+
+            match deunicode::deunicode_char(c) {
+                Some(ascii) if ascii.chars().all(is_pocsag_safe) => ascii.to_string(),
+                _ => c.to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Checks if a character transmits correctly as 7 bit POCSAG alphanumeric text, i.e. it is
+/// printable ASCII.
+fn is_pocsag_safe(c: char) -> bool {
+    c == ' ' || c.is_ascii_graphic()
+}
+
+/// Applies the policy to any characters in the text that are not POCSAG safe.
+fn sanitise(text: String, policy: CharsetPolicy) -> String {
+    if text.chars().all(is_pocsag_safe) {
+        return text;
+    }
+
+    counter!("news_unsafe_characters", "policy" => format!("{policy:?}").to_lowercase())
+        .increment(1);
+
+    match policy {
+        CharsetPolicy::Replace => text
+            .chars()
+            .map(|c| if is_pocsag_safe(c) { c } else { '?' })
+            .collect(),
+        CharsetPolicy::Drop => text.chars().filter(|c| is_pocsag_safe(*c)).collect(),
+        CharsetPolicy::Warn => {
+            warn!("Message contains characters that may not transmit correctly: {text}");
+            text
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use clap::{parser::ValueSource, ArgMatches, Command, Parser};
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+/// A named set of options, keyed by the name of the option with underscores rather than dashes
+/// (e.g. `api_url = "https://..."`, `dry_run = true` or `include_venue = ["Stage A", "Stage B"]`).
+#[derive(Debug, Deserialize)]
+#[serde(transparent)]
+pub(crate) struct Profile {
+    options: BTreeMap<String, ProfileValue>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ProfileValue {
+    Flag(bool),
+    Integer(i64),
+    Float(f64),
+    Text(String),
+    List(Vec<ProfileValue>),
+}
+
+impl ProfileValue {
+    fn values(&self, name: &str) -> anyhow::Result<Vec<String>> {
+        match self {
+            Self::Flag(value) => Ok(vec![value.to_string()]),
+            Self::Integer(value) => Ok(vec![value.to_string()]),
+            Self::Float(value) => Ok(vec![value.to_string()]),
+            Self::Text(value) => Ok(vec![value.clone()]),
+            Self::List(values) => values
+                .iter()
+                .map(|value| match value {
+                    Self::List(_) => Err(anyhow::anyhow!(
+                        "Option {name} in profile cannot contain lists of lists"
+                    )),
+                    value => Ok(value.values(name)?.remove(0)),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Profile {
+    /// Loads a named profile from a TOML file with a table for each profile (e.g. `[emf2024]`).
+    pub(crate) fn load(path: &Path, name: &str) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read profile file {}: {e}", path.display()))?;
+
+        let mut profiles: HashMap<String, Profile> = toml::from_str(&contents)
+            .map_err(|e| anyhow::anyhow!("Invalid profile file {}: {e}", path.display()))?;
+
+        profiles
+            .remove(name)
+            .ok_or_else(|| anyhow::anyhow!("No profile named {name} in {}", path.display()))
+    }
+
+    /// Converts the profile to command line arguments (e.g. `api_url = "x"` to `--api-url=x`),
+    /// leaving out options that `matches` shows were given on the command line or in the
+    /// environment, as those take precedence.
+    fn to_args(&self, command: &Command, matches: &ArgMatches) -> anyhow::Result<Vec<String>> {
+        let mut args = Vec::new();
+
+        for (name, value) in &self.options {
+            let arg = command
+                .get_arguments()
+                .find(|arg| arg.get_id() == name.as_str())
+                .ok_or_else(|| anyhow::anyhow!("Unknown option {name} in profile"))?;
+            let long = arg
+                .get_long()
+                .filter(|_| !["profile", "profile_file"].contains(&name.as_str()))
+                .ok_or_else(|| anyhow::anyhow!("Option {name} cannot be set by a profile"))?;
+
+            if matches!(
+                matches.value_source(name),
+                Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+            ) {
+                continue;
+            }
+
+            if arg.get_action().takes_values() {
+                for value in value.values(name)? {
+                    args.push(format!("--{long}={value}"));
+                }
+            } else {
+                match value {
+                    ProfileValue::Flag(true) => args.push(format!("--{long}")),
+                    ProfileValue::Flag(false) => {}
+                    _ => {
+                        return Err(anyhow::anyhow!(
+                            "Option {name} in profile must be true or false"
+                        ))
+                    }
+                }
+            }
+        }
+
+        Ok(args)
+    }
+}
+
+/// Parses a command line, taking the options that are not given on the command line or in the
+/// environment from the profile named by `--profile`, if there is one.
+pub(crate) fn parse<C: Parser>(args: Vec<OsString>) -> anyhow::Result<C> {
+    // Parsed leniently first to find the profile and which options were given, as options that
+    // are required may be given by the profile. Anything that fails (e.g. --help) is left to the
+    // full parse.
+    let Ok(matches) = C::command().ignore_errors(true).try_get_matches_from(&args) else {
+        return Ok(C::parse_from(args));
+    };
+
+    let Some(name) = matches.get_one::<String>("profile") else {
+        return Ok(C::parse_from(args));
+    };
+    let path = matches
+        .get_one::<PathBuf>("profile_file")
+        .ok_or_else(|| anyhow::anyhow!("No profile file given"))?;
+
+    let profile_args = Profile::load(path, name)?.to_args(&C::command(), &matches)?;
+
+    // Options must come before any subcommand
+    Ok(C::parse_from(
+        args.iter()
+            .take(1)
+            .cloned()
+            .chain(profile_args.into_iter().map(OsString::from))
+            .chain(args.iter().skip(1).cloned()),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Parser)]
+    struct TestCli {
+        #[arg(long)]
+        profile: Option<String>,
+
+        #[arg(long)]
+        profile_file: Option<PathBuf>,
+
+        #[arg(long)]
+        api_url: String,
+
+        #[arg(long, default_value = "emfcamp")]
+        rubric: String,
+
+        #[arg(long, env = "PROFILE_TEST_MAX_LENGTH", default_value = "80")]
+        max_length: usize,
+
+        #[arg(long, default_value = "false")]
+        dry_run: bool,
+
+        #[arg(long)]
+        include_venue: Vec<String>,
+    }
+
+    /// Writes a profile file for a test, returning its path.
+    fn profile_file(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("profile-{name}-{}.toml", std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn parse_args(args: &[&str]) -> anyhow::Result<TestCli> {
+        parse(args.iter().map(OsString::from).collect())
+    }
+
+    const PROFILES: &str = r#"
+[emf2024]
+api_url = "https://www.emfcamp.org/schedule/2024.json"
+rubric = "emf2024"
+max_length = 60
+dry_run = true
+include_venue = ["Stage A", "Stage B"]
+"#;
+
+    #[test]
+    fn options_are_taken_from_the_profile() {
+        let path = profile_file("options", PROFILES);
+
+        let cli = parse_args(&[
+            "test",
+            "--profile=emf2024",
+            &format!("--profile-file={}", path.display()),
+        ])
+        .unwrap();
+
+        assert_eq!(cli.api_url, "https://www.emfcamp.org/schedule/2024.json");
+        assert_eq!(cli.rubric, "emf2024");
+        assert!(cli.dry_run);
+        assert_eq!(cli.include_venue, ["Stage A", "Stage B"]);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn command_line_and_environment_take_precedence() {
+        let path = profile_file("precedence", PROFILES);
+        std::env::set_var("PROFILE_TEST_MAX_LENGTH", "40");
+
+        let cli = parse_args(&[
+            "test",
+            "--profile=emf2024",
+            &format!("--profile-file={}", path.display()),
+            "--rubric=test",
+            "--include-venue=Stage C",
+        ])
+        .unwrap();
+
+        assert_eq!(cli.rubric, "test");
+        assert_eq!(cli.max_length, 40);
+        assert_eq!(cli.include_venue, ["Stage C"]);
+
+        std::env::remove_var("PROFILE_TEST_MAX_LENGTH");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn unknown_options_in_profiles_are_rejected() {
+        let path = profile_file(
+            "unknown",
+            "[emf2024]\napi_url = \"x\"\nrubrik = \"emf2024\"\n",
+        );
+
+        let e = parse_args(&[
+            "test",
+            "--profile=emf2024",
+            &format!("--profile-file={}", path.display()),
+        ])
+        .unwrap_err();
+
+        assert!(e.to_string().contains("rubrik"), "{e}");
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,117 @@
+use crate::dapnet::RateLimited;
+use metrics::counter;
+use rand::Rng;
+use std::{future::Future, time::Duration};
+use tokio::time::Instant;
+use tracing::warn;
+
+/// How requests to DAPNET are timed out and retried.
+#[derive(Debug, Clone)]
+pub(crate) struct RetryPolicy {
+    /// How long to wait for DAPNET to accept each attempt
+    pub(crate) request_timeout: Duration,
+
+    /// Number of times a request is attempted, including the first
+    pub(crate) max_attempts: u32,
+
+    /// Delay before the first retry, doubled for each retry after that
+    pub(crate) initial_delay: Duration,
+
+    /// Longest time to spend on a request across all attempts (or on every request making up a
+    /// call), no retry is started that would finish waiting after this
+    pub(crate) max_duration: Duration,
+}
+
+impl RetryPolicy {
+    /// Delay before the given retry (the first retry being 1), with up to half as much again added
+    /// at random so that failed requests are not all retried at the same time.
+    fn delay(&self, retry: u32) -> Duration {
+        let delay = self
+            .initial_delay
+            .saturating_mul(2_u32.saturating_pow(retry.saturating_sub(1)));
+
+        let jitter = rand::thread_rng().gen_range(0..=delay.as_millis() as u64 / 2);
+
+        delay + Duration::from_millis(jitter)
+    }
+}
+
+/// Makes a request to DAPNET, retrying with exponential backoff until it succeeds or the policy
+/// gives up, in which case the error from the last attempt is returned.
+///
+/// When DAPNET rate limits a request and says when to retry, that is waited for instead of the
+/// backoff delay (still giving up if it would take longer than the policy allows).
+pub(crate) async fn send<T, E, F, Fut>(
+    policy: &RetryPolicy,
+    operation: &'static str,
+    request: F,
+) -> anyhow::Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: Into<anyhow::Error>,
+{
+    send_until(
+        policy,
+        operation,
+        Instant::now() + policy.max_duration,
+        request,
+    )
+    .await
+}
+
+/// Makes a request to DAPNET as [`send`] does, but giving up at `deadline` rather than after the
+/// policy's maximum duration, so that several requests can share one time limit.
+pub(crate) async fn send_until<T, E, F, Fut>(
+    policy: &RetryPolicy,
+    operation: &'static str,
+    deadline: Instant,
+    mut request: F,
+) -> anyhow::Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: Into<anyhow::Error>,
+{
+    let max_attempts = policy.max_attempts.max(1);
+    let mut attempt = 1;
+
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(anyhow::anyhow!("Out of time to send {operation}"));
+        }
+
+        let timeout = policy.request_timeout.min(remaining);
+        let result = match tokio::time::timeout(timeout, request()).await {
+            Ok(result) => result.map_err(Into::into),
+            Err(_) => Err(anyhow::anyhow!("Timed out after {timeout:?}")),
+        };
+
+        let e = match result {
+            Ok(value) => return Ok(value),
+            Err(e) => e,
+        };
+
+        let delay = match e.downcast_ref::<RateLimited>() {
+            Some(RateLimited {
+                retry_after: Some(retry_after),
+            }) => *retry_after,
+            _ => policy.delay(attempt),
+        };
+
+        if attempt >= max_attempts || Instant::now() + delay > deadline {
+            if max_attempts > 1 {
+                warn!("Giving up on {operation} after {attempt} attempts");
+                counter!("dapnet_retries_exhausted", "operation" => operation).increment(1);
+            }
+            return Err(e);
+        }
+
+        warn!("Attempt {attempt}/{max_attempts} of {operation} failed, retrying in {delay:?}: {e}");
+        counter!("dapnet_retries", "operation" => operation).increment(1);
+
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
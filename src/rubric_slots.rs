@@ -0,0 +1,147 @@
+use crate::event_news::normalise_venue_name;
+use clap::ValueEnum;
+use std::collections::{BTreeSet, HashMap};
+use tracing::{info, warn};
+
+const FIRST_SLOT: i8 = 1;
+const LAST_SLOT: i8 = 10;
+
+/// What happens to venues that do not get a rubric slot of their own.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub(crate) enum SlotOverflowPolicy {
+    /// Share the last slot
+    Share,
+    /// Do not announce events at these venues
+    Drop,
+    /// Share the last slot, logging a warning for each announcement
+    Warn,
+}
+
+/// Rubric slots assigned to venues discovered in the schedule.
+pub(crate) struct SlotAssignment {
+    /// Slots keyed by normalised venue name (see [`normalise_venue_name`])
+    slots: HashMap<String, i8>,
+    overflow_policy: SlotOverflowPolicy,
+}
+
+impl SlotAssignment {
+    /// Assigns slots to venues, first in the order given by `priority` then alphabetically for
+    /// any venues not listed there. Venue names are matched loosely, so a priority of "Workshop 1"
+    /// covers "Workshop 1 (NottingHack)".
+    ///
+    /// If there are more venues than slots then the last slot is reserved for overflow (unless the
+    /// overflow policy is to drop the excess venues).
+    pub(crate) fn new(
+        venues: impl IntoIterator<Item = String>,
+        priority: &[String],
+        overflow_policy: SlotOverflowPolicy,
+    ) -> Self {
+        let mut venues: BTreeSet<String> = venues.into_iter().collect();
+
+        let mut ordered: Vec<String> = Vec::new();
+        for venue in priority {
+            let venue = normalise_venue_name(venue);
+            let (matching, rest) = venues
+                .into_iter()
+                .partition(|v| normalise_venue_name(v) == venue);
+            ordered.extend::<BTreeSet<String>>(matching);
+            venues = rest;
+        }
+        ordered.extend(venues);
+
+        let available_slots = (LAST_SLOT - FIRST_SLOT + 1) as usize;
+        let dedicated_slots = match overflow_policy {
+            SlotOverflowPolicy::Drop => available_slots,
+            _ if ordered.len() > available_slots => available_slots - 1,
+            _ => available_slots,
+        };
+
+        let slots: HashMap<String, i8> = ordered
+            .iter()
+            .take(dedicated_slots)
+            .zip(FIRST_SLOT..)
+            .map(|(venue, slot)| {
+                info!("Assigned rubric slot {slot} to {venue}");
+                (normalise_venue_name(venue), slot)
+            })
+            .collect();
+
+        for venue in ordered.iter().skip(dedicated_slots) {
+            warn!("No dedicated rubric slot for {venue} (overflow policy: {overflow_policy:?})");
+        }
+
+        Self {
+            slots,
+            overflow_policy,
+        }
+    }
+
+    /// Gets the slot for a venue, or `None` if events at the venue should not be announced.
+    pub(crate) fn slot_for_venue(&self, venue: &str) -> Option<i8> {
+        match self.slots.get(&normalise_venue_name(venue)) {
+            Some(slot) => Some(*slot),
+            None => match self.overflow_policy {
+                SlotOverflowPolicy::Share => Some(LAST_SLOT),
+                SlotOverflowPolicy::Drop => None,
+                SlotOverflowPolicy::Warn => {
+                    warn!("Venue {venue} is sharing overflow rubric slot {LAST_SLOT}");
+                    Some(LAST_SLOT)
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn venues(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn priority_venues_get_the_first_slots() {
+        let slots = SlotAssignment::new(
+            venues(&["Stage A", "Stage B", "Stage C"]),
+            &venues(&["Stage C"]),
+            SlotOverflowPolicy::Drop,
+        );
+
+        assert_eq!(slots.slot_for_venue("Stage C"), Some(1));
+        assert_eq!(slots.slot_for_venue("Stage A"), Some(2));
+        assert_eq!(slots.slot_for_venue("Stage B"), Some(3));
+    }
+
+    #[test]
+    fn excess_venues_share_the_last_slot() {
+        let names: Vec<String> = (1..=12).map(|i| format!("Venue {i:02}")).collect();
+        let slots = SlotAssignment::new(names, &[], SlotOverflowPolicy::Share);
+
+        assert_eq!(slots.slot_for_venue("Venue 09"), Some(9));
+        assert_eq!(slots.slot_for_venue("Venue 10"), Some(LAST_SLOT));
+        assert_eq!(slots.slot_for_venue("Venue 12"), Some(LAST_SLOT));
+    }
+
+    #[test]
+    fn excess_venues_are_dropped() {
+        let names: Vec<String> = (1..=12).map(|i| format!("Venue {i:02}")).collect();
+        let slots = SlotAssignment::new(names, &[], SlotOverflowPolicy::Drop);
+
+        assert_eq!(slots.slot_for_venue("Venue 10"), Some(10));
+        assert_eq!(slots.slot_for_venue("Venue 11"), None);
+    }
+
+    #[test]
+    fn priority_and_lookup_match_venue_names_loosely() {
+        let slots = SlotAssignment::new(
+            venues(&["Stage A", "Workshop 1 (NottingHack)"]),
+            &venues(&["workshop 1"]),
+            SlotOverflowPolicy::Drop,
+        );
+
+        assert_eq!(slots.slot_for_venue("Workshop 1 (NottingHack)"), Some(1));
+        assert_eq!(slots.slot_for_venue("Workshop 1"), Some(1));
+        assert_eq!(slots.slot_for_venue("stage a"), Some(2));
+    }
+}
@@ -0,0 +1,153 @@
+use chrono::{DateTime, FixedOffset};
+use emfcamp_schedule_api::schedule::event::Event;
+use std::collections::HashMap;
+
+/// An event whose start time or venue has changed.
+#[derive(Debug)]
+pub(crate) struct MovedEvent {
+    /// The event as it now is
+    pub(crate) event: Event,
+    pub(crate) previous_start: DateTime<FixedOffset>,
+    pub(crate) previous_venue: String,
+}
+
+/// Events that changed between two versions of the schedule.
+#[derive(Debug)]
+pub(crate) struct ScheduleChanges {
+    pub(crate) added: usize,
+
+    /// Events no longer in the schedule, as they were when last seen
+    pub(crate) removed: Vec<Event>,
+
+    pub(crate) moved: Vec<MovedEvent>,
+}
+
+impl ScheduleChanges {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.added == 0 && self.removed.is_empty() && self.moved.is_empty()
+    }
+
+    /// Short summary suitable for a page, e.g. "Sched upd: +2 -1 moved 3".
+    pub(crate) fn summary(&self) -> String {
+        format!(
+            "Sched upd: +{} -{} moved {}",
+            self.added,
+            self.removed.len(),
+            self.moved.len()
+        )
+    }
+}
+
+/// Remembers every event in the schedule so that changes can be found when it is next checked.
+#[derive(Default)]
+pub(crate) struct ScheduleChangeTracker {
+    previous: Option<HashMap<u32, Event>>,
+}
+
+impl ScheduleChangeTracker {
+    /// Records the current schedule, returning how it differs from the previous one (or `None` if
+    /// this is the first time it has been seen).
+    pub(crate) fn update(&mut self, events: &[Event]) -> Option<ScheduleChanges> {
+        let current: HashMap<u32, Event> = events
+            .iter()
+            .map(|event| (event.id, event.clone()))
+            .collect();
+
+        let changes = self.previous.take().map(|mut previous| {
+            let added = current
+                .keys()
+                .filter(|id| !previous.contains_key(id))
+                .count();
+
+            let moved = current
+                .values()
+                .filter_map(|event| {
+                    let previous = previous.get(&event.id)?;
+                    (previous.start != event.start || previous.venue != event.venue).then(|| {
+                        MovedEvent {
+                            event: event.clone(),
+                            previous_start: previous.start,
+                            previous_venue: previous.venue.clone(),
+                        }
+                    })
+                })
+                .collect();
+
+            previous.retain(|id, _| !current.contains_key(id));
+
+            ScheduleChanges {
+                added,
+                removed: previous.into_values().collect(),
+                moved,
+            }
+        });
+
+        self.previous = Some(current);
+
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(id: u32, start: &str, venue: &str) -> Event {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "start_date": start,
+            "end_date": "2024-05-30T18:00:00+01:00",
+            "venue": venue,
+            "title": "Opening ceremony",
+            "speaker": "",
+            "description": "",
+            "type": "talk",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn first_schedule_has_no_changes() {
+        let mut tracker = ScheduleChangeTracker::default();
+
+        assert!(tracker
+            .update(&[event(1, "2024-05-30T14:00:00+01:00", "Stage A")])
+            .is_none());
+    }
+
+    #[test]
+    fn changes_between_schedules_are_found() {
+        let mut tracker = ScheduleChangeTracker::default();
+        tracker.update(&[
+            event(1, "2024-05-30T14:00:00+01:00", "Stage A"),
+            event(2, "2024-05-30T15:00:00+01:00", "Stage A"),
+            event(3, "2024-05-30T16:00:00+01:00", "Stage B"),
+        ]);
+
+        let changes = tracker
+            .update(&[
+                event(1, "2024-05-30T14:00:00+01:00", "Stage A"),
+                event(2, "2024-05-30T15:00:00+01:00", "Stage C"),
+                event(4, "2024-05-30T17:00:00+01:00", "Stage B"),
+                event(5, "2024-05-30T17:00:00+01:00", "Stage A"),
+            ])
+            .unwrap();
+
+        assert_eq!(changes.added, 2);
+        assert_eq!(changes.removed.len(), 1);
+        assert_eq!(changes.removed[0].id, 3);
+        assert_eq!(changes.moved.len(), 1);
+        assert_eq!(changes.moved[0].event.id, 2);
+        assert_eq!(changes.moved[0].previous_venue, "Stage A");
+        assert_eq!(changes.summary(), "Sched upd: +2 -1 moved 1");
+    }
+
+    #[test]
+    fn unchanged_schedules_have_no_changes() {
+        let events = [event(1, "2024-05-30T14:00:00+01:00", "Stage A")];
+        let mut tracker = ScheduleChangeTracker::default();
+        tracker.update(&events);
+
+        assert!(tracker.update(&events).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,46 @@
+use crate::overrides::ScheduleOverrides;
+use emfcamp_schedule_api::{schedule::event::Event, Client as ScheduleClient};
+use std::path::PathBuf;
+use tracing::warn;
+use url::Url;
+
+/// Fetches the schedule with the local overrides applied, so that everything working from the
+/// schedule sees the corrected events.
+pub(crate) struct ScheduleSource {
+    client: ScheduleClient,
+    overrides: Option<PathBuf>,
+}
+
+impl ScheduleSource {
+    pub(crate) fn new(api_url: Url, overrides: Option<PathBuf>) -> Self {
+        Self {
+            client: ScheduleClient::new(api_url),
+            overrides,
+        }
+    }
+
+    /// Fetches the events in the schedule, corrected by the overrides, leaving out hidden events.
+    pub(crate) async fn events(&self) -> anyhow::Result<Vec<Event>> {
+        let schedule = self.client.get_schedule().await?;
+        let overrides = self.overrides();
+
+        Ok(schedule
+            .events
+            .into_iter()
+            .filter_map(|mut event| overrides.apply(&mut event).then_some(event))
+            .collect())
+    }
+
+    /// Loads the overrides, which are re-read each time so that corrections can be made while
+    /// running. If they cannot be read the schedule is used as published.
+    pub(crate) fn overrides(&self) -> ScheduleOverrides {
+        let Some(path) = &self.overrides else {
+            return ScheduleOverrides::default();
+        };
+
+        ScheduleOverrides::load(path).unwrap_or_else(|e| {
+            warn!("Failed to load schedule overrides, using the schedule as published: {e}");
+            ScheduleOverrides::default()
+        })
+    }
+}
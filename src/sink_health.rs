@@ -0,0 +1,70 @@
+use chrono::{DateTime, Duration, Utc};
+use metrics::gauge;
+use tracing::{info, warn};
+
+/// Tracks the health of somewhere announcements are sent to, with a circuit breaker that stops
+/// sending for a while after repeated failures.
+pub(crate) struct SinkHealth {
+    name: &'static str,
+    consecutive_failures: usize,
+    breaker_threshold: Option<usize>,
+    breaker_cooldown: Duration,
+    open_until: Option<DateTime<Utc>>,
+}
+
+impl SinkHealth {
+    pub(crate) fn new(
+        name: &'static str,
+        breaker_threshold: Option<usize>,
+        breaker_cooldown: Duration,
+    ) -> Self {
+        gauge!("sink_consecutive_failures", "sink" => name).set(0.0);
+        gauge!("sink_circuit_open", "sink" => name).set(0.0);
+
+        Self {
+            name,
+            consecutive_failures: 0,
+            breaker_threshold,
+            breaker_cooldown,
+            open_until: None,
+        }
+    }
+
+    /// Returns true if sending should be attempted, i.e. the circuit breaker is not open.
+    ///
+    /// Once the cooldown has passed a send is allowed through, if it fails the breaker opens
+    /// again.
+    pub(crate) fn allow(&self, now: DateTime<Utc>) -> bool {
+        self.open_until.is_none_or(|until| now >= until)
+    }
+
+    pub(crate) fn record_success(&mut self, now: DateTime<Utc>) {
+        if self.open_until.take().is_some() {
+            info!("{} circuit breaker closed", self.name);
+        }
+        self.consecutive_failures = 0;
+
+        gauge!("sink_consecutive_failures", "sink" => self.name).set(0.0);
+        gauge!("sink_circuit_open", "sink" => self.name).set(0.0);
+        gauge!("sink_last_success_timestamp_seconds", "sink" => self.name)
+            .set(now.timestamp() as f64);
+    }
+
+    pub(crate) fn record_failure(&mut self, now: DateTime<Utc>) {
+        self.consecutive_failures += 1;
+        gauge!("sink_consecutive_failures", "sink" => self.name)
+            .set(self.consecutive_failures as f64);
+
+        if self
+            .breaker_threshold
+            .is_some_and(|threshold| self.consecutive_failures >= threshold)
+        {
+            warn!(
+                "{} has failed {} times in a row, not sending for {}",
+                self.name, self.consecutive_failures, self.breaker_cooldown
+            );
+            self.open_until = Some(now + self.breaker_cooldown);
+            gauge!("sink_circuit_open", "sink" => self.name).set(1.0);
+        }
+    }
+}
@@ -0,0 +1,30 @@
+use crate::event_news::RubricNews;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+/// Remembers which event's news each rubric slot currently holds.
+#[derive(Default)]
+pub(crate) struct SlotOccupancy {
+    slots: HashMap<(String, Option<i8>), DateTime<Utc>>,
+}
+
+impl SlotOccupancy {
+    /// Returns the start time of the event whose news is in the slot the given news would be
+    /// written to, if that event has not started yet and starts before `event_start`.
+    pub(crate) fn more_imminent_occupant(
+        &self,
+        news: &RubricNews,
+        event_start: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Option<DateTime<Utc>> {
+        self.slots
+            .get(&(news.rubric.clone(), news.number))
+            .copied()
+            .filter(|occupant_start| *occupant_start > now && *occupant_start < event_start)
+    }
+
+    pub(crate) fn record(&mut self, news: &RubricNews, event_start: DateTime<Utc>) {
+        self.slots
+            .insert((news.rubric.clone(), news.number), event_start);
+    }
+}
@@ -0,0 +1,91 @@
+use crate::event_news::RubricNews;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+/// Tracks news for events that have been announced but not yet started, so that a rubric slot
+/// shared by several concurrent events can cycle between them.
+#[derive(Default)]
+pub(crate) struct SlotRotation {
+    slots: HashMap<(String, Option<i8>), RotatingSlot>,
+}
+
+#[derive(Default)]
+struct RotatingSlot {
+    entries: Vec<(DateTime<Utc>, RubricNews)>,
+    next: usize,
+}
+
+impl SlotRotation {
+    pub(crate) fn add(&mut self, event_start: DateTime<Utc>, news: RubricNews) {
+        let slot = self
+            .slots
+            .entry((news.rubric.clone(), news.number))
+            .or_default();
+
+        slot.entries.push((event_start, news));
+
+        // The news that was just sent is the last entry, so the one after it is the first
+        slot.next = 0;
+    }
+
+    /// Removes events that have started and returns the next news item for each slot that still
+    /// has more than one event waiting.
+    pub(crate) fn rotate(&mut self, now: DateTime<Utc>) -> Vec<RubricNews> {
+        self.slots.retain(|_, slot| {
+            slot.entries.retain(|(start, _)| *start > now);
+            !slot.entries.is_empty()
+        });
+
+        self.slots
+            .values_mut()
+            .filter(|slot| slot.entries.len() > 1)
+            .map(|slot| {
+                let idx = slot.next % slot.entries.len();
+                slot.next = idx + 1;
+                slot.entries[idx].1.clone()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn news(number: i8, text: &str) -> RubricNews {
+        RubricNews {
+            rubric: "emfcamp".to_string(),
+            number: Some(number),
+            text: text.to_string(),
+        }
+    }
+
+    fn time(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn shared_slots_cycle_through_waiting_events() {
+        let mut rotation = SlotRotation::default();
+        rotation.add(time("2024-05-30T14:00:00Z"), news(5, "Soldering"));
+        rotation.add(time("2024-05-30T14:00:00Z"), news(5, "Knitting"));
+        rotation.add(time("2024-05-30T14:00:00Z"), news(1, "Opening"));
+
+        let now = time("2024-05-30T13:50:00Z");
+        assert_eq!(rotation.rotate(now), vec![news(5, "Soldering")]);
+        assert_eq!(rotation.rotate(now), vec![news(5, "Knitting")]);
+        assert_eq!(rotation.rotate(now), vec![news(5, "Soldering")]);
+    }
+
+    #[test]
+    fn started_events_leave_the_rotation() {
+        let mut rotation = SlotRotation::default();
+        rotation.add(time("2024-05-30T14:00:00Z"), news(5, "Soldering"));
+        rotation.add(time("2024-05-30T14:30:00Z"), news(5, "Knitting"));
+
+        assert_eq!(rotation.rotate(time("2024-05-30T13:50:00Z")).len(), 1);
+
+        // Only one event is left waiting, so there is nothing to cycle between
+        assert!(rotation.rotate(time("2024-05-30T14:00:00Z")).is_empty());
+    }
+}
@@ -0,0 +1,311 @@
+use crate::{
+    build_info,
+    call::{self, CallSettings},
+    dapnet::Dapnet,
+    schedule_source::ScheduleSource,
+    template::MessageTemplate,
+};
+use chrono::{DateTime, Duration, Utc};
+use clap::ValueEnum;
+use emfcamp_schedule_api::schedule::event::Event;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use tracing::{info, warn};
+
+/// When the startup check page is sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum StartupPageWhen {
+    Always,
+    /// Only when not doing a dry run
+    ExceptDryRun,
+    Never,
+}
+
+impl StartupPageWhen {
+    pub(crate) fn should_send(self, dry_run: bool) -> bool {
+        match self {
+            Self::Always => true,
+            Self::ExceptDryRun => !dry_run,
+            Self::Never => false,
+        }
+    }
+}
+
+/// Where the startup pages are sent and what the check page says.
+#[derive(Clone)]
+pub(crate) struct StartupPageSettings {
+    /// Template for the text of the startup check page (see [`StartupPageFields`])
+    pub(crate) template: MessageTemplate,
+
+    pub(crate) recipients: Vec<String>,
+    pub(crate) transmitter_groups: Vec<String>,
+
+    /// How long to wait for the startup check page to be sent
+    pub(crate) timeout: std::time::Duration,
+
+    /// Fail if the startup check page cannot be sent, rather than carrying on without it
+    pub(crate) required: bool,
+
+    /// Template for the text of the page sent when stopping (see [`ShutdownPageFields`]), no page
+    /// is sent if not set
+    pub(crate) shutdown_template: Option<MessageTemplate>,
+}
+
+/// Fields available in the startup check page template.
+#[derive(Serialize)]
+struct StartupPageFields<'a> {
+    time: String,
+    version: &'a str,
+    git_hash: &'a str,
+    mode: &'a str,
+    /// The number of events today, or "?" if the schedule could not be fetched
+    event_count: String,
+}
+
+/// Fields available in the shutdown page template.
+#[derive(Serialize)]
+struct ShutdownPageFields<'a> {
+    time: String,
+    reason: &'a str,
+}
+
+/// Checks that the DAPNET user is allowed to post news to a rubric, i.e. that they own it or are
+/// an admin.
+///
+/// Failing to look the rubric or user up (e.g. because DAPNET is unreachable) is only warned
+/// about, as that says nothing about whether news can be posted once it is reachable again.
+pub(crate) async fn check_rubric_permission(
+    dapnet: &Dapnet,
+    username: &str,
+    rubric: &str,
+) -> anyhow::Result<()> {
+    info!("Checking permission to post to rubric {rubric}...");
+
+    let rubric_info = match dapnet.get_rubric(rubric).await {
+        Ok(Some(rubric_info)) => rubric_info,
+        Ok(None) => return Err(anyhow::anyhow!("Rubric {rubric} does not exist")),
+        Err(e) => {
+            warn!("Failed to look up rubric {rubric}, not checking permission to post to it: {e}");
+            return Ok(());
+        }
+    };
+
+    if rubric_info.owners.iter().any(|owner| owner == username) {
+        info!("User {username} owns rubric {rubric}");
+        return Ok(());
+    }
+
+    match dapnet.is_admin(username).await {
+        Ok(true) => {
+            info!("User {username} is an admin, so may post to rubric {rubric}");
+            Ok(())
+        }
+        Ok(false) => Err(anyhow::anyhow!(
+            "User {username} does not have permission to post to rubric {rubric} (owners: {})",
+            rubric_info.owners.join(", ")
+        )),
+        Err(e) => {
+            warn!(
+                "User {username} does not own rubric {rubric} and failed to check if they are an admin: {e}"
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Checks that each of the transmitter groups calls will be sent via exists.
+pub(crate) async fn check_transmitter_groups(
+    dapnet: &Dapnet,
+    transmitter_groups: &BTreeSet<&str>,
+) -> anyhow::Result<()> {
+    for name in transmitter_groups {
+        info!("Checking transmitter group {name} exists...");
+
+        dapnet
+            .get_transmitter_group(name)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Transmitter group {name} does not exist"))?;
+    }
+
+    Ok(())
+}
+
+pub(crate) async fn send_startup_page(
+    dapnet: &Dapnet,
+    call_settings: &CallSettings,
+    settings: &StartupPageSettings,
+    mode: &str,
+    schedule: &ScheduleSource,
+    now: DateTime<Utc>,
+    time_offset: Duration,
+) -> anyhow::Result<()> {
+    info!("Checking DAPNET connection...");
+
+    let event_count = if settings.template.uses("event_count") {
+        match schedule.events().await {
+            Ok(events) => todays_events(&events, now + time_offset).len().to_string(),
+            Err(e) => {
+                warn!("Failed to fetch schedule for startup page: {e}");
+                "?".to_string()
+            }
+        }
+    } else {
+        String::new()
+    };
+
+    let text = settings.template.render(StartupPageFields {
+        time: now.format("%d %H:%M %Z").to_string(),
+        version: env!("CARGO_PKG_VERSION"),
+        git_hash: build_info::GIT_HASH,
+        mode,
+        event_count,
+    })?;
+
+    match tokio::time::timeout(
+        settings.timeout,
+        call::send_call(
+            dapnet,
+            call_settings,
+            &text,
+            &settings.recipients,
+            &settings.transmitter_groups,
+        ),
+    )
+    .await
+    {
+        Ok(Ok(())) => {
+            info!("Could send a page, assuming DAPNET connection is working");
+        }
+        Ok(Err(e)) if settings.required => {
+            return Err(anyhow::anyhow!("Failed to send startup page: {e}"));
+        }
+        Ok(Err(e)) => {
+            warn!("Failed to send a page, something's fucky... {e}");
+        }
+        Err(_) if settings.required => {
+            return Err(anyhow::anyhow!(
+                "Startup page not sent within {:?}",
+                settings.timeout
+            ));
+        }
+        Err(_) => {
+            warn!(
+                "Startup page not sent within {:?}, carrying on without it",
+                settings.timeout
+            );
+        }
+    };
+
+    Ok(())
+}
+
+/// Tells the startup page recipients that the scheduler is stopping, so that they do not have to
+/// notice announcements stopping to find out.
+pub(crate) async fn send_shutdown_page(
+    dapnet: &Dapnet,
+    call_settings: &CallSettings,
+    settings: &StartupPageSettings,
+    reason: &str,
+    now: DateTime<Utc>,
+) {
+    let Some(template) = &settings.shutdown_template else {
+        return;
+    };
+
+    let text = match template.render(ShutdownPageFields {
+        time: now.format("%d %H:%M %Z").to_string(),
+        reason,
+    }) {
+        Ok(text) => text,
+        Err(e) => {
+            warn!("Not sending shutdown page: {e}");
+            return;
+        }
+    };
+    info!("Sending shutdown page: {text}");
+
+    match tokio::time::timeout(
+        settings.timeout,
+        call::send_call(
+            dapnet,
+            call_settings,
+            &text,
+            &settings.recipients,
+            &settings.transmitter_groups,
+        ),
+    )
+    .await
+    {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => {
+            warn!("Failed to send shutdown page: {e}");
+        }
+        Err(_) => {
+            warn!(
+                "Shutdown page not sent within {:?}, stopping without it",
+                settings.timeout
+            );
+        }
+    }
+}
+
+/// Sends a page saying how many events there are today and when the first of them starts.
+pub(crate) async fn send_startup_summary_page(
+    dapnet: &Dapnet,
+    call_settings: &CallSettings,
+    settings: &StartupPageSettings,
+    schedule: &ScheduleSource,
+    now: DateTime<Utc>,
+) {
+    let events = match schedule.events().await {
+        Ok(events) => events,
+        Err(e) => {
+            warn!("Failed to fetch schedule for startup summary: {e}");
+            return;
+        }
+    };
+
+    let todays_events = todays_events(&events, now);
+
+    let text = match todays_events.iter().map(|e| e.start).min() {
+        Some(first) => format!(
+            "EMF: {} events today, first at {}",
+            todays_events.len(),
+            first.format("%H:%M")
+        ),
+        None => "EMF: no events today".to_string(),
+    };
+    info!("Startup summary: {text}");
+
+    match tokio::time::timeout(
+        settings.timeout,
+        call::send_call(
+            dapnet,
+            call_settings,
+            &text,
+            &settings.recipients,
+            &settings.transmitter_groups,
+        ),
+    )
+    .await
+    {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => {
+            warn!("Failed to send startup summary page: {e}");
+        }
+        Err(_) => {
+            warn!(
+                "Startup summary page not sent within {:?}, carrying on without it",
+                settings.timeout
+            );
+        }
+    }
+}
+
+/// Finds the events that start today, by the schedule's clock and in the event's own time zone.
+fn todays_events(events: &[Event], now: DateTime<Utc>) -> Vec<&Event> {
+    events
+        .iter()
+        .filter(|e| e.start.date_naive() == now.with_timezone(e.start.offset()).date_naive())
+        .collect()
+}
@@ -0,0 +1,62 @@
+use chrono::{DateTime, FixedOffset, Timelike};
+use emfcamp_schedule_api::Client as ScheduleClient;
+use std::collections::BTreeMap;
+use url::Url;
+
+/// Summarises the schedule: events per venue, day and hour of the day, and when the most events
+/// are running at once.
+pub(crate) async fn run(api_url: Url) -> anyhow::Result<()> {
+    let schedule = ScheduleClient::new(api_url).get_schedule().await?;
+    let events = &schedule.events;
+
+    println!("{} events", events.len());
+
+    let mut per_venue = BTreeMap::<&str, usize>::new();
+    let mut per_day = BTreeMap::new();
+    let mut per_hour = BTreeMap::<u32, usize>::new();
+
+    for event in events {
+        *per_venue.entry(event.venue.as_str()).or_default() += 1;
+        *per_day.entry(event.start.date_naive()).or_insert(0usize) += 1;
+        *per_hour.entry(event.start.hour()).or_default() += 1;
+    }
+
+    println!("\nEvents per venue:");
+    for (venue, count) in &per_venue {
+        println!("  {count:>4}  {venue}");
+    }
+
+    println!("\nEvents per day:");
+    for (day, count) in &per_day {
+        println!("  {count:>4}  {}", day.format("%a %Y-%m-%d"));
+    }
+
+    println!("\nEvents starting per hour of the day:");
+    for (hour, count) in &per_hour {
+        println!("  {count:>4}  {hour:02}:00");
+    }
+
+    // Sweep through event starts and ends in time order, ends first so that back to back events
+    // do not count as overlapping
+    let mut changes: Vec<(DateTime<FixedOffset>, i32)> = events
+        .iter()
+        .flat_map(|event| [(event.start, 1), (event.end, -1)])
+        .collect();
+    changes.sort();
+
+    let mut running = 0;
+    let mut busiest: Option<(i32, DateTime<FixedOffset>)> = None;
+
+    for (time, change) in changes {
+        running += change;
+        if busiest.is_none_or(|(most, _)| running > most) {
+            busiest = Some((running, time));
+        }
+    }
+
+    if let Some((most, time)) = busiest {
+        println!("\nBusiest: {most} events running at once from {time}");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,18 @@
+use std::{collections::HashSet, path::Path};
+
+/// Loads the IDs of events that must never be announced from a file with one ID per line, blank
+/// lines and lines starting with `#` are ignored.
+pub(crate) fn load_suppressed_events(path: &Path) -> anyhow::Result<HashSet<u32>> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read suppress list {}: {e}", path.display()))?;
+
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            line.parse()
+                .map_err(|e| anyhow::anyhow!("Invalid event ID \"{line}\" in suppress list: {e}"))
+        })
+        .collect()
+}
@@ -0,0 +1,125 @@
+use minijinja::{AutoEscape, Environment, UndefinedBehavior};
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+/// Fields available in templates for the text of news and calls announcing events.
+pub(crate) const EVENT_FIELDS: &[&str] = &[
+    "venue", "title", "speaker", "type", "start", "minutes", "relative",
+];
+
+/// Fields available in the template for the startup check page.
+pub(crate) const STARTUP_PAGE_FIELDS: &[&str] =
+    &["time", "version", "git_hash", "mode", "event_count"];
+
+/// Fields available in the template for the shutdown page.
+pub(crate) const SHUTDOWN_PAGE_FIELDS: &[&str] = &["time", "reason"];
+
+/// A template for the text of news or pages, in minijinja syntax (e.g.
+/// `{{ venue }}: {{ title | upper }}`).
+#[derive(Debug, Clone)]
+pub(crate) struct MessageTemplate {
+    source: String,
+
+    /// Fields the template uses
+    fields: BTreeSet<String>,
+}
+
+impl MessageTemplate {
+    /// Parses a template, failing if it is invalid or uses any field other than those given, so
+    /// that mistakes are found at startup rather than when an announcement is made.
+    pub(crate) fn new(source: &str, available_fields: &[&str]) -> anyhow::Result<Self> {
+        let env = environment();
+
+        let template = env
+            .template_from_str(source)
+            .map_err(|e| anyhow::anyhow!("Invalid template: {e}"))?;
+
+        let fields = template.undeclared_variables(false);
+
+        let globals: BTreeSet<&str> = env.globals().map(|(name, _)| name).collect();
+        let unknown: Vec<&str> = fields
+            .iter()
+            .map(String::as_str)
+            .filter(|field| !available_fields.contains(field) && !globals.contains(field))
+            .collect();
+
+        if !unknown.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Unknown template field(s): {} (available: {})",
+                unknown.join(", "),
+                available_fields.join(", ")
+            ));
+        }
+
+        Ok(Self {
+            source: source.to_string(),
+            fields: fields.into_iter().collect(),
+        })
+    }
+
+    /// Checks if the template uses a field, e.g. to avoid working out a value that is costly to
+    /// get when it is not needed.
+    pub(crate) fn uses(&self, field: &str) -> bool {
+        self.fields.contains(field)
+    }
+
+    /// Renders the template with the given field values.
+    ///
+    /// Values are inserted as they are, as the text is sent to pagers rather than displayed as
+    /// HTML, and text inserted into the template is never itself treated as template syntax.
+    pub(crate) fn render(&self, fields: impl Serialize) -> anyhow::Result<String> {
+        environment()
+            .render_str(&self.source, fields)
+            .map_err(|e| anyhow::anyhow!("Failed to render template: {e}"))
+    }
+}
+
+fn environment() -> Environment<'static> {
+    let mut env = Environment::new();
+    env.set_auto_escape_callback(|_| AutoEscape::None);
+    env.set_undefined_behavior(UndefinedBehavior::Strict);
+    env
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn fields_are_rendered() {
+        let template =
+            MessageTemplate::new("{{ venue }}: {{ title | upper }}", EVENT_FIELDS).unwrap();
+
+        let text = template
+            .render(BTreeMap::from([("venue", "Stg A"), ("title", "Opening")]))
+            .unwrap();
+
+        assert_eq!(text, "Stg A: OPENING");
+        assert!(template.uses("venue"));
+        assert!(!template.uses("speaker"));
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        let e = MessageTemplate::new("{{ venue }}: {{ titel }}", EVENT_FIELDS).unwrap_err();
+
+        assert!(e.to_string().contains("titel"), "{e}");
+    }
+
+    #[test]
+    fn invalid_templates_are_rejected() {
+        assert!(MessageTemplate::new("{{ venue", EVENT_FIELDS).is_err());
+    }
+
+    #[test]
+    fn values_are_not_escaped_or_rendered() {
+        let template = MessageTemplate::new("{{ title }}", EVENT_FIELDS).unwrap();
+
+        let text = template
+            .render(BTreeMap::from([("title", "<Fish & Chips> {{ venue }}")]))
+            .unwrap();
+
+        assert_eq!(text, "<Fish & Chips> {{ venue }}");
+    }
+}
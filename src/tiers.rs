@@ -0,0 +1,238 @@
+use crate::event_news::{event_type, normalise_venue_name};
+use clap::ValueEnum;
+use emfcamp_schedule_api::schedule::event::Event;
+
+/// How important an event is, which decides how it is announced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum Tier {
+    Headline,
+    Normal,
+    Low,
+}
+
+/// How events in a tier are announced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum Delivery {
+    /// A call to the tier call recipients
+    Call,
+    /// News in the announcement rubric
+    News,
+    /// Both a call and news
+    Both,
+    /// Not announced
+    Nothing,
+}
+
+impl Delivery {
+    pub(crate) fn includes_news(self) -> bool {
+        matches!(self, Self::News | Self::Both)
+    }
+
+    pub(crate) fn includes_call(self) -> bool {
+        matches!(self, Self::Call | Self::Both)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum TierSelector {
+    Venue(String),
+    EventType(String),
+    Event(u32),
+}
+
+/// Places the events matching a selector into a tier.
+#[derive(Debug, Clone)]
+pub(crate) struct TierRule {
+    tier: Tier,
+    selector: TierSelector,
+}
+
+impl TierRule {
+    /// Replaces a rule for a venue group (`venue:@name`) with a rule for each venue in the group,
+    /// using `expand` to look the group up.
+    pub(crate) fn expand_venue_groups(
+        self,
+        expand: impl Fn(&[String]) -> anyhow::Result<Vec<String>>,
+    ) -> anyhow::Result<Vec<TierRule>> {
+        match self.selector {
+            TierSelector::Venue(venue) => Ok(expand(&[venue])?
+                .into_iter()
+                .map(|venue| TierRule {
+                    tier: self.tier,
+                    selector: TierSelector::Venue(venue),
+                })
+                .collect()),
+            _ => Ok(vec![self]),
+        }
+    }
+}
+
+/// Parses a rule in the form `tier=venue:<name>`, `tier=type:<event type>` or `tier=event:<id>`.
+pub(crate) fn parse_tier_rule(s: &str) -> anyhow::Result<TierRule> {
+    let (tier, selector) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow::anyhow!("Tier rule must be in the form tier=selector"))?;
+
+    let tier = Tier::from_str(tier, true).map_err(|e| anyhow::anyhow!(e))?;
+
+    let selector = match selector.split_once(':') {
+        Some(("venue", venue)) => TierSelector::Venue(venue.to_string()),
+        Some(("type", event_type)) => TierSelector::EventType(event_type.to_lowercase()),
+        Some(("event", id)) => TierSelector::Event(id.parse()?),
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Tier selector must be venue:<name>, type:<event type> or event:<id>"
+            ))
+        }
+    };
+
+    Ok(TierRule { tier, selector })
+}
+
+pub(crate) struct TierSettings {
+    /// Rules deciding the tier of each event, the first matching rule is used and events matching
+    /// no rule are in the normal tier
+    pub(crate) rules: Vec<TierRule>,
+
+    pub(crate) headline: Delivery,
+    pub(crate) normal: Delivery,
+    pub(crate) low: Delivery,
+}
+
+impl TierSettings {
+    /// Gets the tier of an event, matching venues loosely (see [`normalise_venue_name`]).
+    pub(crate) fn tier_for(&self, event: &Event) -> Tier {
+        let event_venue = normalise_venue_name(&event.venue);
+
+        self.rules
+            .iter()
+            .find(|rule| match &rule.selector {
+                TierSelector::Venue(venue) => normalise_venue_name(venue) == event_venue,
+                TierSelector::EventType(event_type_) => *event_type_ == event_type(event),
+                TierSelector::Event(id) => *id == event.id,
+            })
+            .map_or(Tier::Normal, |rule| rule.tier)
+    }
+
+    pub(crate) fn delivery_for(&self, event: &Event) -> Delivery {
+        match self.tier_for(event) {
+            Tier::Headline => self.headline,
+            Tier::Normal => self.normal,
+            Tier::Low => self.low,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(id: u32, venue: &str, kind: &str) -> Event {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "start_date": "2024-05-30T14:00:00+00:00",
+            "end_date": "2024-05-30T15:00:00+00:00",
+            "venue": venue,
+            "title": "Opening ceremony",
+            "speaker": "",
+            "description": "",
+            "type": kind,
+        }))
+        .unwrap()
+    }
+
+    fn settings(rules: &[&str]) -> TierSettings {
+        TierSettings {
+            rules: rules.iter().map(|r| parse_tier_rule(r).unwrap()).collect(),
+            headline: Delivery::Both,
+            normal: Delivery::News,
+            low: Delivery::Nothing,
+        }
+    }
+
+    #[test]
+    fn rules_are_parsed() {
+        assert!(matches!(
+            parse_tier_rule("headline=venue:Stage A").unwrap(),
+            TierRule {
+                tier: Tier::Headline,
+                selector: TierSelector::Venue(venue),
+            } if venue == "Stage A"
+        ));
+        assert!(matches!(
+            parse_tier_rule("Low=type:Workshop").unwrap(),
+            TierRule {
+                tier: Tier::Low,
+                selector: TierSelector::EventType(event_type),
+            } if event_type == "workshop"
+        ));
+        assert!(matches!(
+            parse_tier_rule("normal=event:42").unwrap(),
+            TierRule {
+                tier: Tier::Normal,
+                selector: TierSelector::Event(42),
+            }
+        ));
+    }
+
+    #[test]
+    fn invalid_rules_are_rejected() {
+        assert!(parse_tier_rule("headline").is_err());
+        assert!(parse_tier_rule("urgent=venue:Stage A").is_err());
+        assert!(parse_tier_rule("headline=stage:Stage A").is_err());
+        assert!(parse_tier_rule("headline=event:first").is_err());
+    }
+
+    #[test]
+    fn first_matching_rule_decides_the_tier() {
+        let settings = settings(&[
+            "headline=event:1",
+            "low=type:workshop",
+            "headline=type:talk",
+        ]);
+
+        assert_eq!(
+            settings.tier_for(&event(1, "Stage A", "workshop")),
+            Tier::Headline
+        );
+        assert_eq!(
+            settings.tier_for(&event(2, "Stage A", "workshop")),
+            Tier::Low
+        );
+        assert_eq!(
+            settings.tier_for(&event(3, "Stage A", "talk")),
+            Tier::Headline
+        );
+        assert_eq!(
+            settings.tier_for(&event(4, "Stage A", "other")),
+            Tier::Normal
+        );
+
+        assert_eq!(
+            settings.delivery_for(&event(2, "Stage A", "workshop")),
+            Delivery::Nothing
+        );
+        assert_eq!(
+            settings.delivery_for(&event(4, "Stage A", "other")),
+            Delivery::News
+        );
+    }
+
+    #[test]
+    fn venues_are_matched_loosely() {
+        let settings = settings(&["headline=venue:Workshop 1"]);
+
+        assert_eq!(
+            settings.tier_for(&event(1, "Workshop 1 (NottingHack)", "workshop")),
+            Tier::Headline
+        );
+        assert_eq!(
+            settings.tier_for(&event(2, "workshop 1", "workshop")),
+            Tier::Headline
+        );
+        assert_eq!(
+            settings.tier_for(&event(3, "Workshop 2 (Milliways)", "workshop")),
+            Tier::Normal
+        );
+    }
+}
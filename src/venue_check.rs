@@ -0,0 +1,58 @@
+use crate::{
+    event_news::{self, is_known_venue, NewsSettings},
+    schedule_source::ScheduleSource,
+};
+use std::collections::BTreeMap;
+
+/// Lists every distinct venue in the schedule along with how many events are held there and how
+/// they are announced, flagging venues that are in neither the venue map nor the built in venue
+/// table (and so would be announced using their full schedule name).
+///
+/// If `strict` is set the venue table must have no short name collisions and every venue must be
+/// known, as when starting with `--strict-venue-table`.
+pub(crate) async fn run(
+    schedule: &ScheduleSource,
+    news_settings: &NewsSettings,
+    strict: bool,
+) -> anyhow::Result<()> {
+    event_news::check_venue_table(strict)?;
+
+    let events = schedule.events().await?;
+
+    let mut venues = BTreeMap::<&str, usize>::new();
+    for event in &events {
+        *venues.entry(event.venue.as_str()).or_default() += 1;
+    }
+
+    let mut unknown = 0usize;
+
+    for (venue, events) in &venues {
+        let known = is_known_venue(venue)
+            || news_settings
+                .venue_map
+                .as_ref()
+                .is_some_and(|map| map.get(venue).is_some());
+
+        let announced = match news_settings.venue_details(venue) {
+            Some((short_name, number)) => format!("as <{short_name}> in slot {number}"),
+            None => "not announced".to_string(),
+        };
+
+        if known {
+            println!("  {venue} ({events} events) {announced}");
+        } else {
+            println!("! {venue} ({events} events) {announced} - not in venue table");
+            unknown += 1;
+        }
+    }
+
+    println!("{} venues, {unknown} not in venue table", venues.len());
+
+    if strict && unknown > 0 {
+        return Err(anyhow::anyhow!(
+            "{unknown} venues are not in the venue table"
+        ));
+    }
+
+    Ok(())
+}
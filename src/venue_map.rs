@@ -0,0 +1,295 @@
+use crate::event_news::normalise_venue_name;
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::Path,
+};
+use tracing::{info, warn};
+
+/// How a venue is announced.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct VenueMapEntry {
+    /// Name of the venue in the schedule
+    pub(crate) name: String,
+
+    /// Name used for the venue in announcements
+    pub(crate) short_name: String,
+
+    /// Rubric news number announcements for the venue are sent to
+    pub(crate) number: i8,
+
+    /// Rubric to send news for events at the venue to instead of the announcement rubric
+    #[serde(default)]
+    pub(crate) rubric: Option<String>,
+
+    /// Callsigns to send every announcement for the venue to as a call, as well as any news
+    #[serde(default)]
+    pub(crate) call_recipients: Vec<String>,
+}
+
+/// A named set of venues, which options that take venue names can refer to as `@name`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct VenueGroup {
+    pub(crate) name: String,
+
+    /// Names of the venues in the schedule
+    pub(crate) venues: Vec<String>,
+
+    /// Callsigns to send every announcement for venues in the group to as a call
+    #[serde(default)]
+    pub(crate) call_recipients: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct VenueMapFile {
+    #[serde(rename = "venue")]
+    venues: Vec<VenueMapEntry>,
+
+    #[serde(rename = "group", default)]
+    groups: Vec<VenueGroup>,
+}
+
+/// Venues loaded from a TOML file, used in place of the built in venue table, e.g.:
+///
+/// ```toml
+/// [[venue]]
+/// name = "Stage A"
+/// short_name = "Stg A"
+/// number = 1
+/// rubric = "emfstages"
+///
+/// [[venue]]
+/// name = "Null Sector"
+/// short_name = "Nul Sec"
+/// number = 6
+/// call_recipients = ["M0ABC", "M0XYZ"]
+///
+/// [[group]]
+/// name = "stages"
+/// venues = ["Stage A", "Stage B", "Stage C"]
+/// call_recipients = ["M0DEF"]
+/// ```
+#[derive(Debug)]
+pub(crate) struct VenueMap {
+    venues: Vec<VenueMapEntry>,
+    groups: Vec<VenueGroup>,
+}
+
+impl VenueMap {
+    /// Loads and checks a venue map, failing if any venue has an invalid news number or is
+    /// listed more than once, or any group is empty or defined more than once.
+    ///
+    /// Venues sharing a short name are warned about, or fail the check if `strict` is set.
+    pub(crate) fn load(path: &Path, strict: bool) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read venue map {}: {e}", path.display()))?;
+        let file: VenueMapFile = toml::from_str(&contents)
+            .map_err(|e| anyhow::anyhow!("Invalid venue map {}: {e}", path.display()))?;
+
+        let map = Self {
+            venues: file.venues,
+            groups: file.groups,
+        };
+        map.validate(strict)?;
+
+        info!("Loaded {} venues from {}", map.venues.len(), path.display());
+
+        Ok(map)
+    }
+
+    fn validate(&self, strict: bool) -> anyhow::Result<()> {
+        let mut names = HashSet::new();
+        let mut short_names = BTreeMap::<&str, Vec<&str>>::new();
+
+        for venue in &self.venues {
+            if !(1..=10).contains(&venue.number) {
+                return Err(anyhow::anyhow!(
+                    "Venue {} has news number {}, which must be between 1 and 10",
+                    venue.name,
+                    venue.number
+                ));
+            }
+
+            if venue.short_name.trim().is_empty() {
+                return Err(anyhow::anyhow!("Venue {} has no short name", venue.name));
+            }
+
+            if !names.insert(normalise_venue_name(&venue.name)) {
+                return Err(anyhow::anyhow!(
+                    "Venue {} is in the venue map more than once",
+                    venue.name
+                ));
+            }
+
+            short_names
+                .entry(&venue.short_name)
+                .or_default()
+                .push(&venue.name);
+        }
+
+        let collisions: Vec<String> = short_names
+            .iter()
+            .filter(|(_, venues)| venues.len() > 1)
+            .map(|(short_name, venues)| format!("\"{short_name}\" ({})", venues.join(", ")))
+            .collect();
+
+        if !collisions.is_empty() {
+            if strict {
+                return Err(anyhow::anyhow!(
+                    "Venues share short names: {}",
+                    collisions.join("; ")
+                ));
+            }
+            warn!("Venues share short names: {}", collisions.join("; "));
+        }
+
+        let mut group_names = HashSet::new();
+
+        for group in &self.groups {
+            if group.venues.is_empty() {
+                return Err(anyhow::anyhow!("Venue group {} has no venues", group.name));
+            }
+
+            if !group_names.insert(&group.name) {
+                return Err(anyhow::anyhow!(
+                    "Venue group {} is defined more than once",
+                    group.name
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn venues(&self) -> &[VenueMapEntry] {
+        &self.venues
+    }
+
+    pub(crate) fn groups(&self) -> &[VenueGroup] {
+        &self.groups
+    }
+
+    /// Looks up a venue by its schedule name, matching loosely if there is no exact match.
+    pub(crate) fn get(&self, name: &str) -> Option<&VenueMapEntry> {
+        let normalised = normalise_venue_name(name);
+
+        self.venues
+            .iter()
+            .find(|venue| venue.name == name)
+            .or_else(|| {
+                self.venues
+                    .iter()
+                    .find(|venue| normalise_venue_name(&venue.name) == normalised)
+            })
+    }
+}
+
+/// Replaces each reference to a venue group (`@name`) in a list of venue names with the venues in
+/// the group.
+pub(crate) fn expand_venue_groups(
+    venue_map: Option<&VenueMap>,
+    names: &[String],
+) -> anyhow::Result<Vec<String>> {
+    let mut venues = Vec::new();
+
+    for name in names {
+        let Some(group_name) = name.strip_prefix('@') else {
+            venues.push(name.clone());
+            continue;
+        };
+
+        let venue_map = venue_map.ok_or_else(|| {
+            anyhow::anyhow!("Venue group {name} is used, but venue groups need a venue map")
+        })?;
+
+        let group = venue_map
+            .groups
+            .iter()
+            .find(|group| group.name == group_name)
+            .ok_or_else(|| anyhow::anyhow!("No venue group {group_name} in the venue map"))?;
+
+        venues.extend(group.venues.iter().cloned());
+    }
+
+    Ok(venues)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn venue_map(contents: &str, strict: bool) -> anyhow::Result<VenueMap> {
+        let file: VenueMapFile = toml::from_str(contents)?;
+        let map = VenueMap {
+            venues: file.venues,
+            groups: file.groups,
+        };
+        map.validate(strict)?;
+        Ok(map)
+    }
+
+    const VENUES: &str = r#"
+[[venue]]
+name = "Stage A"
+short_name = "Stg A"
+number = 1
+
+[[venue]]
+name = "Workshop 1 (NottingHack)"
+short_name = "Wksp 1"
+number = 5
+
+[[group]]
+name = "stages"
+venues = ["Stage A", "Stage B"]
+"#;
+
+    #[test]
+    fn venues_are_looked_up_loosely() {
+        let map = venue_map(VENUES, true).unwrap();
+
+        assert_eq!(map.get("Stage A").unwrap().short_name, "Stg A");
+        assert_eq!(map.get("workshop 1").unwrap().short_name, "Wksp 1");
+        assert!(map.get("Stage B").is_none());
+    }
+
+    #[test]
+    fn invalid_venue_maps_are_rejected() {
+        let bad_number = "[[venue]]\nname = \"Stage A\"\nshort_name = \"Stg A\"\nnumber = 11\n";
+        assert!(venue_map(bad_number, false).is_err());
+
+        let duplicate = format!(
+            "{VENUES}\n[[venue]]\nname = \"Workshop 1\"\nshort_name = \"W1\"\nnumber = 6\n"
+        );
+        assert!(venue_map(&duplicate, false).is_err());
+
+        let empty_group = format!("{VENUES}\n[[group]]\nname = \"empty\"\nvenues = []\n");
+        assert!(venue_map(&empty_group, false).is_err());
+    }
+
+    #[test]
+    fn shared_short_names_are_only_rejected_when_strict() {
+        let shared = format!(
+            "{VENUES}\n[[venue]]\nname = \"Stage B\"\nshort_name = \"Stg A\"\nnumber = 2\n"
+        );
+
+        assert!(venue_map(&shared, false).is_ok());
+        assert!(venue_map(&shared, true).is_err());
+    }
+
+    #[test]
+    fn venue_groups_are_expanded() {
+        let map = venue_map(VENUES, true).unwrap();
+        let names = vec!["@stages".to_string(), "Null Sector".to_string()];
+
+        assert_eq!(
+            expand_venue_groups(Some(&map), &names).unwrap(),
+            ["Stage A", "Stage B", "Null Sector"]
+        );
+        assert!(expand_venue_groups(Some(&map), &["@tents".to_string()]).is_err());
+        assert!(expand_venue_groups(None, &names).is_err());
+    }
+}
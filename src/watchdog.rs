@@ -0,0 +1,41 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+
+/// Detects when no announcements have been sent for a long period during the event.
+pub(crate) struct AnnouncementWatchdog {
+    event_days: Vec<NaiveDate>,
+    window: Duration,
+    last_sent: DateTime<Utc>,
+    alerted: bool,
+}
+
+impl AnnouncementWatchdog {
+    pub(crate) fn new(event_days: Vec<NaiveDate>, window: Duration, now: DateTime<Utc>) -> Self {
+        Self {
+            event_days,
+            window,
+            last_sent: now,
+            alerted: false,
+        }
+    }
+
+    pub(crate) fn window(&self) -> Duration {
+        self.window
+    }
+
+    pub(crate) fn record_sent(&mut self, now: DateTime<Utc>) {
+        self.last_sent = now;
+        self.alerted = false;
+    }
+
+    pub(crate) fn record_alerted(&mut self) {
+        self.alerted = true;
+    }
+
+    /// Returns true if nothing has been sent for longer than the window on an event day and the
+    /// operator has not yet been alerted about it.
+    pub(crate) fn is_overdue(&self, now: DateTime<Utc>) -> bool {
+        let event_day = self.event_days.is_empty() || self.event_days.contains(&now.date_naive());
+
+        event_day && !self.alerted && now - self.last_sent >= self.window
+    }
+}